@@ -0,0 +1,52 @@
+// In this program, we draw a navmesh and a path through it to SVG, to see what was generated.
+// Enable the `png` feature to also draw it to PNG.
+
+use std::{env, fs::File};
+
+use glam::{UVec2, Vec2};
+use seldom_map_nav::{export::MeshDrawing, mesh::generate_navmesh, prelude::*};
+
+#[rustfmt::skip]
+static TILEMAP: &[&[bool]] = &[
+    &[true,  true,  true,  true,  false],
+    &[true,  false, false, true,  true],
+    &[true,  true,  true,  false, true],
+    &[true,  true,  false, false, true],
+];
+const MAP_SIZE: UVec2 = UVec2::new(5, 4);
+const TILE_SIZE: Vec2 = Vec2::splat(1.);
+
+fn main() {
+    let navability = |position: UVec2| match TILEMAP[position.y as usize][position.x as usize] {
+        true => Navability::Navable,
+        false => Navability::Solid,
+    };
+
+    let navmesh = generate_navmesh(MAP_SIZE, TILE_SIZE, navability, 0.2).unwrap();
+    let path = PathMesh::find_path(
+        &navmesh,
+        Vec2::new(1.5, 3.5),
+        Vec2::new(4.5, 3.5),
+        NavQuery::Accuracy,
+        NavPathMode::Accuracy,
+    )
+    .unwrap();
+
+    let mut drawing = MeshDrawing::new(MAP_SIZE, TILE_SIZE, &navmesh)
+        .with_path(path)
+        .with_tiles(navability);
+    drawing.scale = 64.;
+    // 0, 0 is at the top left of the tilemap
+    drawing.y_up = false;
+
+    let svg = env::temp_dir().join("navmesh.svg");
+    drawing.write_svg(File::create(&svg).unwrap()).unwrap();
+    println!("Wrote {}", svg.display());
+
+    #[cfg(feature = "png")]
+    {
+        let png = env::temp_dir().join("navmesh.png");
+        drawing.write_png(File::create(&png).unwrap()).unwrap();
+        println!("Wrote {}", png.display());
+    }
+}
@@ -0,0 +1,137 @@
+// In this game, a crowd of players follows one flow field to wherever you click.
+// Right click to add another target. Each player heads to whichever target is nearest.
+
+use bevy::{prelude::*, sprite::Anchor};
+use rand::{thread_rng, Rng};
+use seldom_map_nav::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, MapNavPlugin::<Transform>::default()))
+        .init_resource::<CursorPos>()
+        .add_systems(Startup, init)
+        .add_systems(Update, (update_cursor_pos, move_target).chain())
+        .run();
+}
+
+const MAP_SIZE: UVec2 = UVec2::new(24, 24);
+const TILE_SIZE: Vec2 = Vec2::new(32., 32.);
+const PLAYER_CLEARANCE: f32 = 8.;
+const PLAYER_COUNT: usize = 50;
+
+fn init(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2d,
+        Transform::from_translation((MAP_SIZE.as_vec2() * TILE_SIZE / 2.).extend(999.9)),
+    ));
+
+    let mut rng = thread_rng();
+    let tilemap = [(); (MAP_SIZE.x * MAP_SIZE.y) as usize].map(|_| match rng.gen_bool(0.8) {
+        true => Navability::Navable,
+        false => Navability::Solid,
+    });
+    let navability = |pos: UVec2| tilemap[(pos.y * MAP_SIZE.x + pos.x) as usize];
+
+    let tile_image = asset_server.load("tile.png");
+    let mut player_positions = Vec::new();
+    for x in 0..MAP_SIZE.x {
+        for y in 0..MAP_SIZE.y {
+            let pos = UVec2::new(x, y);
+            if let Navability::Navable = navability(pos) {
+                let pos = UVec2::new(x, y).as_vec2() * TILE_SIZE;
+                player_positions.push(pos);
+
+                commands.spawn((
+                    Sprite {
+                        image: tile_image.clone(),
+                        anchor: Anchor::BottomLeft,
+                        ..default()
+                    },
+                    Transform::from_translation(pos.extend(0.)),
+                ));
+            }
+        }
+    }
+
+    let map = commands
+        .spawn(Navmeshes::generate(MAP_SIZE, TILE_SIZE, navability, [PLAYER_CLEARANCE]).unwrap())
+        .id();
+
+    // Spawn the flow field toward the center of the map. It's generated once, and shared by every
+    // player that follows it.
+    let flow = commands
+        .spawn((
+            FlowTarget,
+            Flow::new(
+                map,
+                PLAYER_CLEARANCE,
+                None,
+                PathTarget::Points(vec![MAP_SIZE.as_vec2() * TILE_SIZE / 2.]),
+                NavQuery::Accuracy,
+            ),
+        ))
+        .id();
+
+    let player_image = asset_server.load("player.png");
+    for _ in 0..PLAYER_COUNT {
+        let pos = player_positions[rng.gen_range(0..player_positions.len())];
+        commands.spawn((
+            // Follow the flow field instead of finding a path with `Pathfind`
+            FollowFlow(flow),
+            Nav::new(150.),
+            // Spread out instead of stacking up on the target
+            Crowding::new(24., 1., 0.),
+            Sprite::from_image(player_image.clone()),
+            Transform::from_translation((pos + TILE_SIZE / 2.).extend(1.)),
+        ));
+    }
+}
+
+// Move the flow field's target to wherever you left click, or add a target where you right click
+fn move_target(
+    mut flows: Query<&mut Flow, With<FlowTarget>>,
+    cursor_pos: Res<CursorPos>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) -> Result {
+    let Some(cursor_pos) = **cursor_pos else {
+        return Ok(());
+    };
+
+    let mut flow = flows.single_mut()?;
+    if mouse.just_pressed(MouseButton::Left) {
+        flow.target = PathTarget::Points(vec![cursor_pos]);
+    } else if mouse.just_pressed(MouseButton::Right) {
+        if let PathTarget::Points(targets) = &mut flow.target {
+            targets.push(cursor_pos);
+        }
+    } else {
+        return Ok(());
+    }
+
+    // Regenerate the field now
+    flow.next_repath = default();
+
+    Ok(())
+}
+
+// The code after this comment is not related to `seldom_map_nav`
+
+#[derive(Component)]
+struct FlowTarget;
+
+#[derive(Default, Deref, DerefMut, Resource)]
+struct CursorPos(Option<Vec2>);
+
+fn update_cursor_pos(
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window>,
+    mut position: ResMut<CursorPos>,
+) -> Result {
+    let (camera, transform) = cameras.single()?;
+    **position = windows
+        .single()?
+        .cursor_position()
+        .and_then(|cursor_pos| camera.viewport_to_world_2d(transform, cursor_pos).ok());
+
+    Ok(())
+}
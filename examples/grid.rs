@@ -0,0 +1,159 @@
+// In this game, one player moves through the navmesh and the other moves tile-by-tile.
+// Left click to move the navmesh player, and right click to move the grid player.
+
+use bevy::{prelude::*, sprite::Anchor};
+use rand::{thread_rng, Rng};
+use seldom_map_nav::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            // Add a plugin for each `PathMesh` you use. Navigators use the one that matches
+            // the `Navmeshes` on their map.
+            MapNavPlugin::<Transform>::default(),
+            MapNavPlugin::<Transform, TileGrid>::default(),
+        ))
+        .init_resource::<CursorPos>()
+        .add_systems(Startup, init)
+        .add_systems(Update, (update_cursor_pos, move_players).chain())
+        .run();
+}
+
+const MAP_SIZE: UVec2 = UVec2::new(24, 24);
+const TILE_SIZE: Vec2 = Vec2::new(32., 32.);
+const PLAYER_CLEARANCE: f32 = 8.;
+
+fn init(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2d,
+        Transform::from_translation((MAP_SIZE.as_vec2() * TILE_SIZE / 2.).extend(999.9)),
+    ));
+
+    let mut rng = thread_rng();
+    let tilemap = [(); (MAP_SIZE.x * MAP_SIZE.y) as usize].map(|_| match rng.gen_bool(0.8) {
+        true => Navability::Navable,
+        false => Navability::Solid,
+    });
+    let navability = |pos: UVec2| tilemap[(pos.y * MAP_SIZE.x + pos.x) as usize];
+
+    let tile_image = asset_server.load("tile.png");
+    let mut player_pos = default();
+    for x in 0..MAP_SIZE.x {
+        for y in 0..MAP_SIZE.y {
+            let pos = UVec2::new(x, y);
+            if let Navability::Navable = navability(pos) {
+                let pos = UVec2::new(x, y).as_vec2() * TILE_SIZE;
+                player_pos = pos;
+
+                commands.spawn((
+                    Sprite {
+                        image: tile_image.clone(),
+                        anchor: Anchor::BottomLeft,
+                        ..default()
+                    },
+                    Transform::from_translation(pos.extend(0.)),
+                ));
+            }
+        }
+    }
+
+    // Spawn the same tilemap twice: once with navmeshes, and once with tile grids
+    commands.spawn((
+        MeshMap,
+        Navmeshes::generate(MAP_SIZE, TILE_SIZE, navability, [PLAYER_CLEARANCE]).unwrap(),
+    ));
+    commands.spawn((
+        GridMap,
+        Navmeshes::generate_grid(
+            MAP_SIZE,
+            TILE_SIZE,
+            navability,
+            [PLAYER_CLEARANCE],
+            // Move like a king without squeezing past corners, and find paths with jump point
+            // search
+            GridSettings {
+                connectivity: Connectivity::Eight(CornerCutting::Never),
+                jump_points: true,
+            },
+        ),
+    ));
+
+    for (player, offset) in [(Player::Mesh, Vec2::ZERO), (Player::Grid, Vec2::splat(4.))] {
+        commands.spawn((
+            player,
+            Sprite {
+                image: asset_server.load("player.png"),
+                ..default()
+            },
+            Transform::from_translation((player_pos + TILE_SIZE / 2. + offset).extend(1.)),
+        ));
+    }
+}
+
+fn move_players(
+    mut commands: Commands,
+    players: Query<(Entity, &Player)>,
+    mesh_maps: Query<Entity, With<MeshMap>>,
+    grid_maps: Query<Entity, With<GridMap>>,
+    cursor_pos: Res<CursorPos>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) -> Result {
+    let Some(cursor_pos) = **cursor_pos else {
+        return Ok(());
+    };
+
+    for (entity, player) in &players {
+        // Which map the navigator uses decides how its path is found
+        let map = match player {
+            Player::Mesh if mouse.just_pressed(MouseButton::Left) => mesh_maps.single()?,
+            Player::Grid if mouse.just_pressed(MouseButton::Right) => grid_maps.single()?,
+            _ => continue,
+        };
+
+        commands.entity(entity).insert(NavBundle {
+            pathfind: Pathfind::new(
+                map,
+                PLAYER_CLEARANCE,
+                None,
+                PathTarget::Static(cursor_pos),
+                NavQuery::Accuracy,
+                NavPathMode::Accuracy,
+            ),
+            nav: Nav::new(200.),
+        });
+    }
+
+    Ok(())
+}
+
+// The code after this comment is not related to `seldom_map_nav`
+
+#[derive(Component)]
+enum Player {
+    Mesh,
+    Grid,
+}
+
+#[derive(Component)]
+struct MeshMap;
+
+#[derive(Component)]
+struct GridMap;
+
+#[derive(Default, Deref, DerefMut, Resource)]
+struct CursorPos(Option<Vec2>);
+
+fn update_cursor_pos(
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window>,
+    mut position: ResMut<CursorPos>,
+) -> Result {
+    let (camera, transform) = cameras.single()?;
+    **position = windows
+        .single()?
+        .cursor_position()
+        .and_then(|cursor_pos| camera.viewport_to_world_2d(transform, cursor_pos).ok());
+
+    Ok(())
+}
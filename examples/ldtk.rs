@@ -0,0 +1,61 @@
+// In this program, we load navability from the levels of an LDtk world, and generate paths
+// through them
+
+use std::path::Path;
+
+use glam::{UVec2, Vec2};
+use seldom_map_nav::{
+    ldtk::{load_levels, IntGridNavability, LdtkLevel},
+    prelude::*,
+};
+
+fn main() {
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/ldtk/world.ldtk");
+
+    // In the `Collision` IntGrid layer, 1 is a wall, 2 is water, and 3 is grass
+    let navability = IntGridNavability::new(
+        "Collision",
+        [
+            (1, Navability::Solid),
+            (2, Navability::Solid),
+            (3, Navability::Navable),
+        ],
+    );
+    let levels = load_levels(project, &navability).unwrap();
+
+    for level in &levels {
+        print_level(level);
+    }
+
+    // Each level's navmeshes are in world coordinates, so we can find the level that contains
+    // a position in the world, and find a path through it
+    let (from, to) = (Vec2::new(296., 72.), Vec2::new(488., 232.));
+    let level = levels.iter().find(|level| level.contains(from)).unwrap();
+    let navmeshes = level.navmeshes([0., 4.]).unwrap();
+    println!(
+        "Path through {}: {:?}",
+        level.identifier,
+        PathMesh::find_path(
+            navmeshes.mesh(4.).unwrap(),
+            from,
+            to,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy,
+        )
+        .unwrap()
+    );
+}
+
+fn print_level(level: &LdtkLevel) {
+    println!("{} at {}", level.identifier, level.offset);
+    for y in 0..level.map_size.y {
+        let row = (0..level.map_size.x)
+            .map(|x| match level.navability(UVec2::new(x, y)) {
+                Navability::Navable => '.',
+                Navability::Solid => '#',
+            })
+            .collect::<String>();
+        println!("{row}");
+    }
+    println!();
+}
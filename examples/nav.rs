@@ -1,4 +1,5 @@
-// In this game, the player navigates to wherever you click
+// In this game, the player navigates to wherever you click. Right click to make it run away
+// from the cursor instead.
 
 use bevy::{prelude::*, sprite::Anchor};
 use rand::{thread_rng, Rng};
@@ -75,7 +76,7 @@ fn init(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
-// Navigate the player to wherever you click
+// Navigate the player to wherever you click, or away from where you right click
 fn move_player(
     mut commands: Commands,
     players: Query<Entity, With<Player>>,
@@ -83,25 +84,37 @@ fn move_player(
     cursor_pos: Res<CursorPos>,
     mouse: Res<ButtonInput<MouseButton>>,
 ) -> Result {
-    if mouse.just_pressed(MouseButton::Left) {
-        if let Some(cursor_pos) = **cursor_pos {
-            // Clicked somewhere on the screen!
-            // Add `NavBundle` to start navigating to that position
-            // If you want to write your own movement, but still want paths generated,
-            // only insert `Pathfind`.
-            commands.entity(players.single()?).insert(NavBundle {
-                pathfind: Pathfind::new(
-                    navmesheses.single()?,
-                    PLAYER_CLEARANCE,
-                    None,
-                    PathTarget::Static(cursor_pos),
-                    NavQuery::Accuracy,
-                    NavPathMode::Accuracy,
-                ),
-                nav: Nav::new(200.),
-            });
+    let Some(cursor_pos) = **cursor_pos else {
+        return Ok(());
+    };
+
+    let target = if mouse.just_pressed(MouseButton::Left) {
+        PathTarget::Static(cursor_pos)
+    } else if mouse.just_pressed(MouseButton::Right) {
+        // Run to the point furthest from the cursor that's within 300 units of travel
+        PathTarget::Flee {
+            from: cursor_pos,
+            budget: 300.,
         }
-    }
+    } else {
+        return Ok(());
+    };
+
+    // Clicked somewhere on the screen!
+    // Add `NavBundle` to start navigating
+    // If you want to write your own movement, but still want paths generated,
+    // only insert `Pathfind`.
+    commands.entity(players.single()?).insert(NavBundle {
+        pathfind: Pathfind::new(
+            navmesheses.single()?,
+            PLAYER_CLEARANCE,
+            None,
+            target,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy,
+        ),
+        nav: Nav::new(200.),
+    });
 
     Ok(())
 }
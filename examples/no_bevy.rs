@@ -66,4 +66,29 @@ fn main() {
             .find_path(START, END, NavQuery::Accuracy, NavPathMode::Accuracy)
             .unwrap()
     );
+
+    // Navmeshes can also hold any other `PathMesh`. `TileGrid` is a grid that paths from tile
+    // to tile, which is useful if your navigators move tile-by-tile.
+    let grids = Navmeshes::generate_grid(
+        MAP_SIZE,
+        TILE_SIZE,
+        navability,
+        [0., 0.4],
+        GridSettings::default(),
+    );
+
+    // Print a path through tile centers with at least 0. clearance
+    println!(
+        "{:?}",
+        grids
+            .mesh(0.)
+            .unwrap()
+            .find_path(
+                Vec2::new(START.x, START.y),
+                Vec2::new(END.x, END.y),
+                NavQuery::Accuracy,
+                NavPathMode::Accuracy
+            )
+            .unwrap()
+    );
 }
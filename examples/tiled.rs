@@ -0,0 +1,80 @@
+// In this program, we load navability from Tiled maps, and generate paths through them
+
+use std::path::Path;
+
+use glam::{UVec2, Vec2};
+use seldom_map_nav::{
+    prelude::*,
+    tiled::{TiledMap, TiledNavability},
+};
+
+fn main() {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/tiled");
+
+    // Walls are tiles with the `solid` property in the `walls.tsx` tileset
+    let dungeon = TiledMap::load(
+        assets.join("dungeon.tmx"),
+        &TiledNavability::Property("solid".to_string()),
+    )
+    .unwrap();
+    print_map(&dungeon);
+
+    // Furniture is drawn as objects on the `Collision` layer
+    let furniture = TiledMap::load(
+        assets.join("dungeon.tmx"),
+        &TiledNavability::Layer("Collision".to_string()),
+    )
+    .unwrap();
+    print_map(&furniture);
+
+    // Combine them to navigate around both
+    let mut combined = dungeon.clone();
+    for (tile, &furniture) in combined.tiles.iter_mut().zip(&furniture.tiles) {
+        if furniture == Navability::Solid {
+            *tile = Navability::Solid;
+        }
+    }
+    print_map(&combined);
+    print_path(&combined, Vec2::new(48., 48.), Vec2::new(464., 272.));
+
+    // Isometric maps are navigated on a square navmesh. Positions are converted to and from
+    // Tiled's pixel coordinates.
+    let courtyard = TiledMap::load(
+        assets.join("courtyard.tmx"),
+        &TiledNavability::Property("solid".to_string()),
+    )
+    .unwrap();
+    print_map(&courtyard);
+    print_path(&courtyard, Vec2::new(500., 100.), Vec2::new(500., 425.));
+}
+
+fn print_map(map: &TiledMap) {
+    for y in 0..map.map_size.y {
+        let row = (0..map.map_size.x)
+            .map(|x| match map.navability(UVec2::new(x, y)) {
+                Navability::Navable => '.',
+                Navability::Solid => '#',
+            })
+            .collect::<String>();
+        println!("{row}");
+    }
+    println!();
+}
+
+// Prints a path between the given points, in Tiled's pixel coordinates
+fn print_path(map: &TiledMap, from: Vec2, to: Vec2) {
+    let navmeshes = map.navmeshes([0., 8.]).unwrap();
+    let path = PathMesh::find_path(
+        navmeshes.mesh(8.).unwrap(),
+        map.from_pixel(from),
+        map.from_pixel(to),
+        NavQuery::Accuracy,
+        NavPathMode::Accuracy,
+    )
+    .unwrap();
+
+    for point in path {
+        print!("{} ", map.to_pixel(point));
+    }
+    println!("\n");
+}
@@ -0,0 +1,547 @@
+use bevy_ecs::{entity::EntityHashMap, query::QueryEntityError};
+
+use crate::{
+    crowd::{crowd, NeighborIndex},
+    mesh::PathMesh,
+    nav::{go_to, NavStep},
+    prelude::*,
+};
+
+pub(crate) fn plug<P: Position2, M: PathMesh>(app: &mut App) {
+    app.add_systems(
+        Update,
+        keep_on_mesh::<P, M>
+            .after(avoid::<P>)
+            .after(crowd::<P>)
+            .in_set(NavStep::Avoid),
+    );
+}
+
+/// Add this component to a navigator with [`Nav`] to have it steer around other navigators
+/// with `Avoidance`, using optimal reciprocal collision avoidance (ORCA). Each navigator's
+/// radius is the clearance of its [`Pathfind`] or [`Flow`], and it is kept on the navmesh
+/// for that clearance.
+#[derive(Clone, Component, Copy, Debug, Reflect)]
+pub struct Avoidance {
+    /// How far away other navigators are considered
+    pub neighbor_distance: f32,
+    /// How far ahead, in seconds, to avoid collisions. Larger values avoid earlier, but are
+    /// more cautious.
+    pub time_horizon: f32,
+    /// Velocity chosen last frame, after avoiding other navigators
+    pub velocity: Vec2,
+}
+
+impl Avoidance {
+    /// Create an `Avoidance`
+    pub fn new(neighbor_distance: f32, time_horizon: f32) -> Self {
+        Self {
+            neighbor_distance,
+            time_horizon,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+// Navigator's state at the start of the frame
+struct Agent {
+    entity: Entity,
+    pos: Vec2,
+    radius: f32,
+    velocity: Vec2,
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn avoid<P: Position2>(
+    mut navs: Query<(
+        Entity,
+        &mut P,
+        &mut Nav,
+        &mut Avoidance,
+        Option<&Pathfind>,
+        Option<&FollowFlow>,
+        Option<&mut VelocityNav>,
+    )>,
+    flows: Query<&Flow>,
+    index: Res<NeighborIndex<P>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    if delta <= 0. {
+        return;
+    }
+
+    let agents = navs
+        .iter()
+        .filter_map(|(entity, _, _, avoidance, pathfind, follow_flow, _)| {
+            Some((
+                entity,
+                Agent {
+                    entity,
+                    pos: index.get(entity)?,
+                    radius: radius(pathfind, follow_flow, &flows)?,
+                    velocity: avoidance.velocity,
+                },
+            ))
+        })
+        .collect::<EntityHashMap<_>>();
+
+    let mut lines = Vec::new();
+    for agent in agents.values() {
+        let Ok((_, mut position, mut nav, mut avoidance, .., velocity_nav)) =
+            navs.get_mut(agent.entity)
+        else {
+            continue;
+        };
+
+        lines.clear();
+        lines.extend(
+            index
+                .within(agent.pos, avoidance.neighbor_distance)
+                .filter(|&(other, _)| other != agent.entity)
+                .filter_map(|(other, _)| agents.get(&other))
+                .map(|other| orca_line(agent, other, avoidance.time_horizon, delta)),
+        );
+
+        let velocity = solve(&lines, nav.speed, nav.velocity);
+        nav.velocity = velocity;
+        avoidance.velocity = velocity;
+        go_to(&mut position, velocity_nav, agent.pos + velocity * delta);
+    }
+}
+
+fn radius(
+    pathfind: Option<&Pathfind>,
+    follow_flow: Option<&FollowFlow>,
+    flows: &Query<&Flow>,
+) -> Option<f32> {
+    match (pathfind, follow_flow) {
+        (Some(pathfind), _) => Some(pathfind.radius),
+        (None, Some(&FollowFlow(flow))) => flows.get(flow).ok().map(|flow| flow.radius),
+        (None, None) => None,
+    }
+}
+
+// Pulls navigators that avoidance or crowding pushed off the navmesh back onto it
+#[allow(clippy::type_complexity)]
+fn keep_on_mesh<P: Position2, M: PathMesh>(
+    mut navs: Query<
+        (
+            &mut P,
+            &mut Nav,
+            Option<&Pathfind>,
+            Option<&FollowFlow>,
+            Option<&mut VelocityNav>,
+        ),
+        Or<(With<Avoidance>, With<Crowding>)>,
+    >,
+    flows: Query<&Flow>,
+    meshes: Query<&Navmeshes<M>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    if delta <= 0. {
+        return;
+    }
+
+    for (mut position, mut nav, pathfind, follow_flow, velocity_nav) in &mut navs {
+        let (map, radius, query) = match (pathfind, follow_flow) {
+            (Some(pathfind), _) => (pathfind.map, pathfind.radius, pathfind.query),
+            (None, Some(&FollowFlow(flow))) => {
+                let Ok(flow) = flows.get(flow) else {
+                    continue;
+                };
+                (flow.map, flow.radius, flow.query)
+            }
+            (None, None) => continue,
+        };
+
+        let navmeshes = meshes.get(map);
+        if let Err(QueryEntityError::QueryDoesNotMatch(..)) = navmeshes {
+            // The map is handled by another `PathMesh`
+            continue;
+        }
+
+        let Some(mesh) = navmeshes.ok().and_then(|navmeshes| navmeshes.mesh(radius)) else {
+            continue;
+        };
+
+        let pos = match &velocity_nav {
+            Some(velocity_nav) => velocity_nav.expected.unwrap_or(position.get()),
+            None => position.get(),
+        };
+        let Some(on_mesh) = mesh.closest_point(pos, query) else {
+            continue;
+        };
+        if on_mesh == pos {
+            continue;
+        }
+
+        match velocity_nav {
+            // The body only moves by its velocity, so steer it back, no faster than the
+            // navigator's speed
+            Some(mut velocity_nav) => {
+                let velocity = (nav.velocity + (on_mesh - pos) / delta).clamp_length_max(nav.speed);
+                velocity_nav.expected = Some(pos + (velocity - nav.velocity) * delta);
+                nav.velocity = velocity;
+            }
+            // Being pulled back isn't how the navigator chose to move, so its velocity is kept
+            None => position.set(on_mesh),
+        }
+    }
+}
+
+// Half-plane of velocities, to the left of `direction` through `point`
+#[derive(Clone, Copy)]
+struct Line {
+    point: Vec2,
+    direction: Vec2,
+}
+
+// Velocities that avoid colliding with `other` within `time_horizon`, assuming that it
+// takes half of the responsibility
+fn orca_line(agent: &Agent, other: &Agent, time_horizon: f32, delta: f32) -> Line {
+    let relative_pos = other.pos - agent.pos;
+    let relative_velocity = agent.velocity - other.velocity;
+    let distance_squared = relative_pos.length_squared();
+    let combined_radius = agent.radius + other.radius;
+    let combined_radius_squared = combined_radius * combined_radius;
+
+    let (direction, u) = if distance_squared > combined_radius_squared {
+        // Vector from the cutoff center to the relative velocity
+        let w = relative_velocity - relative_pos / time_horizon;
+        let w_length_squared = w.length_squared();
+        let dot = w.dot(relative_pos);
+
+        if dot < 0. && dot * dot > combined_radius_squared * w_length_squared {
+            // Project onto the cutoff circle
+            let w_length = w_length_squared.sqrt();
+            let unit_w = w / w_length;
+            (
+                -unit_w.perp(),
+                (combined_radius / time_horizon - w_length) * unit_w,
+            )
+        } else {
+            // Project onto the legs of the velocity obstacle
+            let leg = (distance_squared - combined_radius_squared).sqrt();
+            let direction = match relative_pos.perp_dot(w) > 0. {
+                true => Vec2::new(
+                    relative_pos.x * leg - relative_pos.y * combined_radius,
+                    relative_pos.x * combined_radius + relative_pos.y * leg,
+                ),
+                false => -Vec2::new(
+                    relative_pos.x * leg + relative_pos.y * combined_radius,
+                    -relative_pos.x * combined_radius + relative_pos.y * leg,
+                ),
+            } / distance_squared;
+
+            (
+                direction,
+                relative_velocity.dot(direction) * direction - relative_velocity,
+            )
+        }
+    } else {
+        // Already colliding, so separate within this frame
+        let w = relative_velocity - relative_pos / delta;
+        let w_length = w.length();
+        let unit_w = w.normalize_or(Vec2::X);
+        (
+            -unit_w.perp(),
+            (combined_radius / delta - w_length) * unit_w,
+        )
+    };
+
+    Line {
+        point: agent.velocity + u / 2.,
+        direction,
+    }
+}
+
+// Finds the velocity closest to `preferred` that satisfies all the `lines` and is no faster than
+// `max_speed`. If there is none, finds the velocity that violates them the least.
+fn solve(lines: &[Line], max_speed: f32, preferred: Vec2) -> Vec2 {
+    let mut velocity = Vec2::ZERO;
+    let failed = solve_2d(lines, max_speed, preferred, false, &mut velocity);
+    if failed < lines.len() {
+        solve_3d(lines, failed, max_speed, &mut velocity);
+    }
+
+    velocity
+}
+
+const EPSILON: f32 = 0.00001;
+
+// Optimizes along line `line` of `lines`, subject to the lines before it
+fn solve_1d(
+    lines: &[Line],
+    line: usize,
+    max_speed: f32,
+    optimal: Vec2,
+    optimize_direction: bool,
+    result: &mut Vec2,
+) -> bool {
+    let Line { point, direction } = lines[line];
+    let dot = point.dot(direction);
+    let discriminant = dot * dot + max_speed * max_speed - point.length_squared();
+    if discriminant < 0. {
+        // The speed limit invalidates the line
+        return false;
+    }
+
+    let discriminant = discriminant.sqrt();
+    let mut left = -dot - discriminant;
+    let mut right = -dot + discriminant;
+
+    for other in &lines[..line] {
+        let denominator = direction.perp_dot(other.direction);
+        let numerator = other.direction.perp_dot(point - other.point);
+
+        if denominator.abs() <= EPSILON {
+            // The lines are nearly parallel
+            if numerator < 0. {
+                return false;
+            }
+
+            continue;
+        }
+
+        let t = numerator / denominator;
+        match denominator >= 0. {
+            true => right = right.min(t),
+            false => left = left.max(t),
+        }
+
+        if left > right {
+            return false;
+        }
+    }
+
+    let t = match optimize_direction {
+        true => match optimal.dot(direction) > 0. {
+            true => right,
+            false => left,
+        },
+        false => direction.dot(optimal - point).clamp(left, right),
+    };
+    *result = point + t * direction;
+
+    true
+}
+
+// Optimizes subject to all of the `lines`. Returns the index of the line that could not be
+// satisfied, or the number of lines if all were.
+fn solve_2d(
+    lines: &[Line],
+    max_speed: f32,
+    optimal: Vec2,
+    optimize_direction: bool,
+    result: &mut Vec2,
+) -> usize {
+    *result = match optimize_direction {
+        true => optimal * max_speed,
+        false => optimal.clamp_length_max(max_speed),
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.direction.perp_dot(line.point - *result) > 0. {
+            // The result does not satisfy this line
+            let previous = *result;
+            if !solve_1d(lines, index, max_speed, optimal, optimize_direction, result) {
+                *result = previous;
+                return index;
+            }
+        }
+    }
+
+    lines.len()
+}
+
+// Minimizes the greatest violation of the lines from `begin` on, when they cannot all be satisfied
+fn solve_3d(lines: &[Line], begin: usize, max_speed: f32, result: &mut Vec2) {
+    let mut distance = 0.;
+    let mut projected = Vec::new();
+
+    for (index, line) in lines.iter().enumerate().skip(begin) {
+        if line.direction.perp_dot(line.point - *result) <= distance {
+            continue;
+        }
+
+        projected.clear();
+        for other in &lines[..index] {
+            let determinant = line.direction.perp_dot(other.direction);
+            let point = if determinant.abs() <= EPSILON {
+                if line.direction.dot(other.direction) > 0. {
+                    // The lines point in the same direction
+                    continue;
+                }
+
+                (line.point + other.point) / 2.
+            } else {
+                line.point
+                    + other.direction.perp_dot(line.point - other.point) / determinant
+                        * line.direction
+            };
+
+            projected.push(Line {
+                point,
+                direction: (other.direction - line.direction).normalize_or_zero(),
+            });
+        }
+
+        let previous = *result;
+        if solve_2d(&projected, max_speed, line.direction.perp(), true, result) < projected.len() {
+            // This should only happen due to floating point error
+            *result = previous;
+        }
+
+        distance = line.direction.perp_dot(line.point - *result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    use super::*;
+
+    fn agent(pos: Vec2, velocity: Vec2) -> Agent {
+        Agent {
+            entity: Entity::PLACEHOLDER,
+            pos,
+            radius: 0.5,
+            velocity,
+        }
+    }
+
+    // Whether the velocity is on the allowed side of the line
+    fn satisfies(line: Line, velocity: Vec2) -> bool {
+        line.direction.perp_dot(line.point - velocity) <= EPSILON
+    }
+
+    #[test]
+    fn unconstrained_velocity_is_preferred() {
+        assert_eq!(solve(&[], 2., Vec2::new(1., 1.)), Vec2::new(1., 1.));
+        assert_eq!(solve(&[], 2., Vec2::new(4., 0.)), Vec2::new(2., 0.));
+    }
+
+    #[test]
+    fn velocity_is_projected_onto_half_plane() {
+        // Velocities with y of at least 1
+        let line = Line {
+            point: Vec2::Y,
+            direction: Vec2::X,
+        };
+        assert_eq!(solve(&[line], 2., Vec2::new(1., 0.)), Vec2::new(1., 1.));
+        assert_eq!(solve(&[line], 2., Vec2::new(1., 1.5)), Vec2::new(1., 1.5));
+
+        // The speed limit holds along the line
+        let velocity = solve(&[line], 2., Vec2::new(3., 0.));
+        assert!(satisfies(line, velocity));
+        assert!((velocity.length() - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn conflicting_half_planes_are_violated_least() {
+        // Velocities with y of at least 1, and at most -1
+        let lines = [
+            Line {
+                point: Vec2::Y,
+                direction: Vec2::X,
+            },
+            Line {
+                point: Vec2::NEG_Y,
+                direction: Vec2::NEG_X,
+            },
+        ];
+        let velocity = solve(&lines, 2., Vec2::X);
+        assert!(velocity.y.abs() < 1e-4);
+        assert!(velocity.length() <= 2. + 1e-4);
+    }
+
+    #[test]
+    fn approaching_agents_avoid_each_other() {
+        let time_horizon = 5.;
+        let a = agent(Vec2::ZERO, Vec2::X);
+        let b = agent(Vec2::new(4., 0.1), Vec2::NEG_X);
+
+        let line_a = orca_line(&a, &b, time_horizon, 0.1);
+        let line_b = orca_line(&b, &a, time_horizon, 0.1);
+        let velocity_a = solve(&[line_a], 1., a.velocity);
+        let velocity_b = solve(&[line_b], 1., b.velocity);
+        assert!(satisfies(line_a, velocity_a));
+        assert!(satisfies(line_b, velocity_b));
+        // They pass on opposite sides
+        assert!(velocity_a.y * velocity_b.y < 0.);
+
+        // With the new velocities, they don't collide within the time horizon
+        let relative_pos = b.pos - a.pos;
+        let relative_velocity = velocity_b - velocity_a;
+        let closest = (-relative_pos.dot(relative_velocity) / relative_velocity.length_squared())
+            .clamp(0., time_horizon);
+        let distance = (relative_pos + relative_velocity * closest).length();
+        assert!(distance >= a.radius + b.radius - 1e-3);
+    }
+
+    #[test]
+    fn distant_agents_are_unconstrained() {
+        let a = agent(Vec2::ZERO, Vec2::X);
+        let b = agent(Vec2::new(0., 10.), Vec2::X);
+        let line = orca_line(&a, &b, 2., 0.1);
+        assert!(satisfies(line, a.velocity));
+        assert_eq!(solve(&[line], 1., a.velocity), a.velocity);
+    }
+
+    #[test]
+    fn overlapping_agents_separate() {
+        let a = agent(Vec2::ZERO, Vec2::ZERO);
+        let b = agent(Vec2::new(0.5, 0.), Vec2::ZERO);
+        let velocity = solve(&[orca_line(&a, &b, 2., 0.1)], 10., Vec2::ZERO);
+        assert!(velocity.x < 0.);
+    }
+
+    #[test]
+    fn pulling_back_onto_the_mesh_keeps_the_speed() {
+        let mut app = testing::app();
+        app.add_plugins(NavVelocityPlugin::<testing::Velocity>::default());
+        let map = testing::map(&mut app, UVec2::splat(10), |_| false, [0.5]);
+        let target = PathTarget::Static(Vec2::new(5., 8.));
+        // Off the navmesh, which is shrunk by its clearance, but close enough to find a path
+        let start = Vec2::new(5., 0.1);
+        let navs = [
+            testing::spawn(
+                &mut app,
+                start,
+                (
+                    testing::pathfind(map, 0.5, target.clone()),
+                    Nav::new(1.),
+                    Avoidance::new(2., 1.),
+                ),
+            ),
+            testing::spawn(
+                &mut app,
+                start + Vec2::X * 3.,
+                (
+                    testing::pathfind(map, 0.5, target),
+                    Nav::new(1.),
+                    Avoidance::new(2., 1.),
+                    VelocityNav::new(10.),
+                    testing::Velocity::default(),
+                ),
+            ),
+        ];
+
+        for _ in 0..3 {
+            app.update();
+            for nav in navs {
+                let speed = testing::get::<Nav>(&app, nav).velocity.length();
+                assert!(speed <= 1. + 1e-4, "speed {speed}");
+                let avoidance = testing::get::<Avoidance>(&app, nav).velocity.length();
+                assert!(avoidance <= 1. + 1e-4, "avoidance speed {avoidance}");
+            }
+        }
+
+        // The navigator that moves itself was pulled back onto the navmesh
+        assert!(testing::position(&app, navs[0]).y >= 0.5);
+        assert!(testing::get::<testing::Velocity>(&app, navs[1]).0.length() <= 1. + 1e-4);
+    }
+}
@@ -0,0 +1,403 @@
+//! Command-line tool for checking the navmeshes generated for a tilemap, without running a game
+
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, Read},
+    path::Path,
+    process::ExitCode,
+    time::Instant,
+};
+
+use glam::{UVec2, Vec2};
+use navmesh::NavMesh;
+use seldom_map_nav::{export::MeshDrawing, json::NavmeshesData, prelude::*};
+
+const USAGE: &str = "\
+Usage: seldom_map_nav <COMMAND> <MAP> [OPTIONS]
+
+Generates navmeshes for a tilemap. The map is an ASCII grid, where `#` is a solid tile and
+anything else is navigable, or a PNG image, where dark pixels are solid tiles and light or
+transparent pixels are navigable. The bottom row is at y = 0, and y increases upward,
+like in the library.
+
+Commands:
+  stats                 Print statistics about the map and each navmesh
+  export <OUTPUT>       Write the navmeshes to OUTPUT. A `.json` file holds every navmesh's
+                        vertices and triangles, and can be loaded with the library's `json`
+                        feature. A `.svg` or `.png` file draws the navmesh for the first
+                        clearance.
+  path [<FROM> <TO>]    Find a path with each clearance, from FROM to TO, such as `1.5,3.5`.
+                        Without FROM and TO, reads a pair of points from each line of stdin.
+
+Options:
+  --tile-size <SIZE>    Size of each tile, such as `1` or `16,8` [default: 1]
+  --clearance <LIST>    Comma-separated clearances to generate navmeshes for [default: 0]
+  --scale <PIXELS>      Pixels per unit of distance in SVG and PNG output [default: 16 per tile]
+  --path <FROM> <TO>    Draw a path in SVG and PNG output
+";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Parsed command-line arguments
+struct Args {
+    command: String,
+    map: String,
+    operands: Vec<String>,
+    tile_size: Vec2,
+    clearances: Vec<f32>,
+    scale: Option<f32>,
+    path: Option<(Vec2, Vec2)>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Args, Box<dyn Error>> {
+    let mut args = args.into_iter();
+    let mut positional = Vec::new();
+    let mut tile_size = Vec2::ONE;
+    let mut clearances = vec![0.];
+    let mut scale = None;
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                std::process::exit(0);
+            }
+            "--tile-size" => {
+                let size = parse_list(&value()?)?;
+                tile_size = match size[..] {
+                    [size] => Vec2::splat(size),
+                    [x, y] => Vec2::new(x, y),
+                    _ => return Err("tile size must have 1 or 2 components".into()),
+                };
+            }
+            "--clearance" => clearances = parse_list(&value()?)?,
+            "--scale" => scale = Some(value()?.parse()?),
+            "--path" => path = Some((parse_point(&value()?)?, parse_point(&value()?)?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{arg}`").into()),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    Ok(Args {
+        command: positional.next().ok_or("missing command")?,
+        map: positional.next().ok_or("missing map")?,
+        operands: positional.collect(),
+        tile_size,
+        clearances,
+        scale,
+        path,
+    })
+}
+
+fn parse_list(list: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+    Ok(list
+        .split(',')
+        .map(|item| item.trim().parse())
+        .collect::<Result<_, _>>()?)
+}
+
+fn parse_point(point: &str) -> Result<Vec2, Box<dyn Error>> {
+    match parse_list(point)?[..] {
+        [x, y] => Ok(Vec2::new(x, y)),
+        _ => Err(format!("`{point}` is not a point like `1.5,3.5`").into()),
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args)?;
+    let (map_size, tiles) = load_map(Path::new(&args.map))?;
+    let navability = |position: UVec2| tiles[(position.y * map_size.x + position.x) as usize];
+
+    let start = Instant::now();
+    let navmeshes = Navmeshes::generate(
+        map_size,
+        args.tile_size,
+        navability,
+        args.clearances.clone(),
+    )?;
+    let elapsed = start.elapsed();
+
+    match (args.command.as_str(), &args.operands[..]) {
+        ("stats", []) => {
+            let solid = tiles
+                .iter()
+                .filter(|&&tile| tile == Navability::Solid)
+                .count();
+            println!(
+                "map: {}x{} tiles of size {}x{}, {} navigable, {solid} solid",
+                map_size.x,
+                map_size.y,
+                args.tile_size.x,
+                args.tile_size.y,
+                tiles.len() - solid,
+            );
+            println!(
+                "generated {} navmeshes in {elapsed:?}",
+                navmeshes.mesh_count()
+            );
+            for (clearance, mesh) in meshes(&navmeshes) {
+                let area = mesh.areas().iter().map(|area| area.size).sum::<f32>();
+                println!(
+                    "clearance {clearance}: {} vertices, {} triangles, {} islands, {area} area",
+                    mesh.vertices().len(),
+                    mesh.triangles().len(),
+                    mesh.find_triangle_islands().len(),
+                );
+            }
+        }
+        ("export", [output]) => {
+            let extension = Path::new(output)
+                .extension()
+                .and_then(|extension| extension.to_str());
+            if extension == Some("json") {
+                fs::write(
+                    output,
+                    NavmeshesData::new(map_size, args.tile_size, &navmeshes).to_json(),
+                )?;
+                return Ok(());
+            }
+
+            let (_, mesh) = meshes(&navmeshes).next().ok_or("no navmeshes")?;
+            let mut drawing =
+                MeshDrawing::new(map_size, args.tile_size, mesh).with_tiles(navability);
+            if let Some(scale) = args.scale {
+                drawing.scale = scale;
+            }
+            if let Some((from, to)) = args.path {
+                drawing = drawing.with_path(find_path(mesh, from, to).unwrap_or_default());
+            }
+
+            match extension {
+                Some("svg") => drawing.write_svg(File::create(output)?)?,
+                Some("png") => drawing.write_png(File::create(output)?)?,
+                _ => {
+                    return Err(
+                        format!("`{output}` is not a `.json`, `.svg`, or `.png` file").into(),
+                    )
+                }
+            }
+        }
+        ("path", [from, to]) => print_paths(&navmeshes, parse_point(from)?, parse_point(to)?),
+        ("path", []) => {
+            for line in io::stdin().lock().lines() {
+                let line = line?;
+                let points = line
+                    .split_whitespace()
+                    .map(parse_point)
+                    .collect::<Result<Vec<_>, _>>();
+                match points.as_deref() {
+                    Ok(&[from, to]) => print_paths(&navmeshes, from, to),
+                    Ok([]) => (),
+                    Ok(_) => eprintln!("error: expected two points, like `1.5,3.5 4.5,3.5`"),
+                    Err(error) => eprintln!("error: {error}"),
+                }
+            }
+        }
+        ("stats" | "export" | "path", _) => return Err("wrong number of arguments".into()),
+        (command, _) => return Err(format!("unknown command `{command}`").into()),
+    }
+
+    Ok(())
+}
+
+// Loads the navability of each tile, row by row from the bottom, from an ASCII grid or PNG image
+fn load_map(path: &Path) -> Result<(UVec2, Vec<Navability>), Box<dyn Error>> {
+    let map = match path.extension().is_some_and(|extension| extension == "png") {
+        true => read_image(File::open(path)?)?,
+        false => read_grid(&fs::read_to_string(path)?),
+    };
+
+    match map.0.x {
+        0 => Err(format!("`{}` has no tiles", path.display()).into()),
+        _ => Ok(map),
+    }
+}
+
+fn read_grid(text: &str) -> (UVec2, Vec<Navability>) {
+    let rows = text.lines().collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    // Short rows are padded with solid tiles. The last line is the bottom row.
+    let tiles = rows
+        .iter()
+        .rev()
+        .flat_map(|row| {
+            row.chars()
+                .map(|tile| match tile {
+                    '#' => Navability::Solid,
+                    _ => Navability::Navable,
+                })
+                .chain(std::iter::repeat(Navability::Solid))
+                .take(width)
+        })
+        .collect();
+
+    (UVec2::new(width as u32, rows.len() as u32), tiles)
+}
+
+fn read_image(image: impl Read) -> Result<(UVec2, Vec<Navability>), Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(image);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+
+    let channels = info.color_type.samples();
+    // Images are stored from the top row down
+    let tiles = pixels[..info.buffer_size()]
+        .chunks_exact(info.line_size)
+        .rev()
+        .flat_map(|row| row.chunks_exact(channels))
+        .map(|pixel| {
+            let (color, alpha) = match info.color_type {
+                png::ColorType::GrayscaleAlpha | png::ColorType::Rgba => {
+                    (&pixel[..channels - 1], pixel[channels - 1])
+                }
+                _ => (pixel, u8::MAX),
+            };
+            let brightness =
+                color.iter().map(|&channel| channel as u32).sum::<u32>() / color.len() as u32;
+            match alpha >= 128 && brightness < 128 {
+                true => Navability::Solid,
+                false => Navability::Navable,
+            }
+        })
+        .collect();
+
+    Ok((UVec2::new(info.width, info.height), tiles))
+}
+
+// Each navmesh with its clearance
+fn meshes(navmeshes: &Navmeshes) -> impl Iterator<Item = (f32, &NavMesh)> {
+    (0..navmeshes.mesh_count())
+        .filter_map(|mesh| Some((navmeshes.clearance_at(mesh)?, navmeshes.mesh_at(mesh)?)))
+}
+
+fn find_path(mesh: &NavMesh, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+    PathMesh::find_path(mesh, from, to, NavQuery::Accuracy, NavPathMode::Accuracy)
+}
+
+fn print_paths(navmeshes: &Navmeshes, from: Vec2, to: Vec2) {
+    for (clearance, mesh) in meshes(navmeshes) {
+        let Some(path) = find_path(mesh, from, to) else {
+            println!("clearance {clearance}: no path");
+            continue;
+        };
+
+        let length = path
+            .windows(2)
+            .map(|waypoints| waypoints[0].distance(waypoints[1]))
+            .sum::<f32>();
+        let waypoints = path
+            .iter()
+            .map(|waypoint| format!("{},{}", waypoint.x, waypoint.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("clearance {clearance}: length {length}: {waypoints}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<Args, Box<dyn Error>> {
+        parse_args(args.split_whitespace().map(String::from).collect())
+    }
+
+    #[test]
+    fn args_are_parsed() {
+        let parsed = args(
+            "export map.txt --tile-size 16,8 out.svg --clearance 0,0.5 --scale 4 \
+             --path 1.5,2.5 3,4",
+        )
+        .unwrap();
+        assert_eq!(parsed.command, "export");
+        assert_eq!(parsed.map, "map.txt");
+        assert_eq!(parsed.operands, ["out.svg"]);
+        assert_eq!(parsed.tile_size, Vec2::new(16., 8.));
+        assert_eq!(parsed.clearances, [0., 0.5]);
+        assert_eq!(parsed.scale, Some(4.));
+        assert_eq!(parsed.path, Some((Vec2::new(1.5, 2.5), Vec2::new(3., 4.))));
+
+        let parsed = args("stats map.txt --tile-size 2").unwrap();
+        assert_eq!(parsed.tile_size, Vec2::splat(2.));
+        assert_eq!(parsed.clearances, [0.]);
+        assert_eq!(parsed.scale, None);
+        assert_eq!(parsed.path, None);
+    }
+
+    #[test]
+    fn bad_args_are_rejected() {
+        for bad in [
+            "stats",
+            "stats map.txt --clearance",
+            "stats map.txt --tile-size 1,2,3",
+            "stats map.txt --clearance a",
+            "stats map.txt --path 1,2",
+            "stats map.txt --path 1 2,3",
+            "stats map.txt --verbose",
+        ] {
+            assert!(args(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn grids_start_at_the_bottom_row() {
+        use Navability::{Navable as O, Solid as X};
+
+        let (size, tiles) = read_grid("#..\n.\n..#\n");
+        assert_eq!(size, UVec2::new(3, 3));
+        // Short rows are padded with solid tiles
+        assert_eq!(tiles, [O, O, X, O, X, X, X, O, O]);
+        assert_eq!(read_grid("").0.x, 0);
+    }
+
+    #[test]
+    fn dark_opaque_pixels_are_solid() {
+        use Navability::{Navable as O, Solid as X};
+
+        // 4x2, with the bottom row being black, gray just below and above half brightness,
+        // and transparent black
+        let pixels = [
+            [255, 255, 255, 255],
+            [255, 255, 255, 255],
+            [255, 255, 255, 255],
+            [0, 0, 0, 255],
+            [0, 0, 0, 255],
+            [127, 127, 127, 255],
+            [128, 128, 128, 255],
+            [0, 0, 0, 0],
+        ];
+        let mut image = Vec::new();
+        let mut encoder = png::Encoder::new(&mut image, 4, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(pixels.as_flattened())
+            .unwrap();
+
+        let (size, tiles) = read_image(&image[..]).unwrap();
+        assert_eq!(size, UVec2::new(4, 2));
+        assert_eq!(tiles, [X, X, O, O, O, O, O, X]);
+    }
+}
@@ -0,0 +1,232 @@
+use std::marker::PhantomData;
+
+use bevy_ecs::entity::EntityHashMap;
+use bevy_platform::collections::HashMap;
+
+use crate::{nav::go_to, prelude::*};
+
+/// Spatial hash of the positions of navigators with [`Nav`], for finding their neighbors
+/// quickly. It is updated each frame before navigation, and is available to your systems.
+/// The type parameter accepts the navigators' position component.
+#[derive(Debug, Resource)]
+pub struct NeighborIndex<P: Position2 = Transform> {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+    positions: EntityHashMap<Vec2>,
+    _position: PhantomData<P>,
+}
+
+impl<P: Position2> Default for NeighborIndex<P> {
+    fn default() -> Self {
+        Self::new(64.)
+    }
+}
+
+impl<P: Position2> NeighborIndex<P> {
+    /// Create an empty `NeighborIndex`. Searches are quickest when `cell_size` is around the
+    /// distance you search within. Panics if `cell_size` isn't positive.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(
+            cell_size > 0.,
+            "`NeighborIndex` cell size must be positive, but is {cell_size}",
+        );
+
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            positions: EntityHashMap::default(),
+            _position: PhantomData,
+        }
+    }
+
+    /// Size of each cell of the hash
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Add an entity at the given position
+    pub fn insert(&mut self, entity: Entity, pos: Vec2) {
+        self.cells
+            .entry(self.cell(pos))
+            .or_default()
+            .push((entity, pos));
+        self.positions.insert(entity, pos);
+    }
+
+    /// Remove every entity
+    pub fn clear(&mut self) {
+        // Keep the cells that are still in use, so they don't need to be allocated again
+        self.cells.retain(|_, entities| {
+            let used = !entities.is_empty();
+            entities.clear();
+            used
+        });
+        self.positions.clear();
+    }
+
+    /// Position of the given entity, if it is indexed
+    pub fn get(&self, entity: Entity) -> Option<Vec2> {
+        self.positions.get(&entity).copied()
+    }
+
+    /// Entities within `radius` of `pos`, and their positions
+    pub fn within(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = self.cell(pos - radius);
+        let max = self.cell(pos + radius);
+        let radius_squared = radius * radius;
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |&(_, other)| other.distance_squared(pos) <= radius_squared)
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+}
+
+pub(crate) fn index_neighbors<P: Position2>(
+    mut index: ResMut<NeighborIndex<P>>,
+    navs: Query<(Entity, &P), With<Nav>>,
+) {
+    index.clear();
+    for (entity, position) in &navs {
+        index.insert(entity, position.get());
+    }
+}
+
+/// Add this component to a navigator with [`Nav`] to have it keep its distance from nearby
+/// navigators, and stay near them. This is cheaper than [`Avoidance`], and keeps navigators
+/// from stacking up when they share a target.
+#[derive(Clone, Component, Copy, Debug, Reflect)]
+pub struct Crowding {
+    /// How far away other navigators are considered. If it isn't positive, none are.
+    pub radius: f32,
+    /// How strongly to steer away from nearby navigators, as a fraction of [`Nav::speed`]
+    pub separation: f32,
+    /// How strongly to steer toward the center of nearby navigators, as a fraction of
+    /// [`Nav::speed`]
+    pub cohesion: f32,
+}
+
+impl Crowding {
+    /// Create a `Crowding`
+    pub fn new(radius: f32, separation: f32, cohesion: f32) -> Self {
+        Self {
+            radius,
+            separation,
+            cohesion,
+        }
+    }
+}
+
+pub(crate) fn crowd<P: Position2>(
+    mut navs: Query<(
+        Entity,
+        &mut P,
+        &mut Nav,
+        &Crowding,
+        Option<&mut VelocityNav>,
+    )>,
+    index: Res<NeighborIndex<P>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    if delta <= 0. {
+        return;
+    }
+
+    for (entity, mut position, mut nav, crowding, velocity_nav) in &mut navs {
+        // Steering is scaled by the radius
+        if crowding.radius <= 0. {
+            continue;
+        }
+
+        let Some(start) = index.get(entity) else {
+            continue;
+        };
+
+        let mut separation = Vec2::ZERO;
+        let mut center = Vec2::ZERO;
+        let mut neighbors = 0;
+        for (_, other) in index
+            .within(start, crowding.radius)
+            .filter(|&(other, _)| other != entity)
+        {
+            let offset = start - other;
+            let distance = offset.length();
+            if distance > 0. {
+                separation += offset / distance * (1. - distance / crowding.radius);
+            }
+
+            center += other;
+            neighbors += 1;
+        }
+
+        if neighbors == 0 {
+            continue;
+        }
+
+        let cohesion = (center / neighbors as f32 - start) / crowding.radius;
+        let steering =
+            (separation * crowding.separation + cohesion * crowding.cohesion) * nav.speed;
+        nav.velocity = (nav.velocity + steering).clamp_length_max(nav.speed);
+        go_to(&mut position, velocity_nav, start + nav.velocity * delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(cell_size: f32) -> (NeighborIndex, Vec<(Entity, Vec2)>) {
+        let mut index = NeighborIndex::new(cell_size);
+        let entities = (0..100)
+            .map(|i| {
+                let entity = Entity::from_raw(i);
+                // Spread around the origin, so some are in negative cells
+                let pos = Vec2::new((i % 10) as f32 * 3.7 - 17., (i / 10) as f32 * 2.9 - 13.);
+                index.insert(entity, pos);
+                (entity, pos)
+            })
+            .collect();
+        (index, entities)
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        for cell_size in [0.5, 4., 64.] {
+            let (index, entities) = index(cell_size);
+            for (pos, radius) in [(Vec2::ZERO, 5.), (Vec2::new(-10., 7.), 3.), (Vec2::ONE, 0.)] {
+                let mut found = index.within(pos, radius).collect::<Vec<_>>();
+                let mut expected = entities
+                    .iter()
+                    .copied()
+                    .filter(|&(_, other)| other.distance(pos) <= radius)
+                    .collect::<Vec<_>>();
+                found.sort_by_key(|&(entity, _)| entity);
+                expected.sort_by_key(|&(entity, _)| entity);
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn clear_removes_every_entity() {
+        let (mut index, entities) = index(4.);
+        assert_eq!(index.get(entities[42].0), Some(entities[42].1));
+
+        index.clear();
+        assert_eq!(index.get(entities[42].0), None);
+        assert_eq!(index.within(Vec2::ZERO, 100.).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cell_size_must_be_positive() {
+        NeighborIndex::<Transform>::new(0.);
+    }
+}
@@ -0,0 +1,214 @@
+//! Gizmo rendering of navmeshes and paths, for debugging
+
+use std::marker::PhantomData;
+
+use bevy_color::{Alpha, Color, Hsla};
+use bevy_ecs::entity::EntityHashMap;
+use bevy_gizmos::{config::GizmoConfigGroup, gizmos::Gizmos, AppGizmoBuilder};
+use navmesh::NavMesh;
+
+use crate::{
+    mesh::{boundary_edges, PathMesh},
+    prelude::*,
+};
+
+/// Add to your app to draw each [`Navmeshes`] and each [`Pathfind`] path with gizmos.
+/// The first type parameter accepts the position component used by your navigators. The second
+/// accepts the [`PathMesh`] stored in your map's [`Navmeshes`]. Requires Bevy's gizmo
+/// plugin, which is in its `DefaultPlugins`. Configure it through the [`NavGizmos`] config group.
+#[derive(Debug)]
+pub struct MapNavDebugPlugin<P: Position2 = Transform, M: PathMesh = NavMesh>(PhantomData<(P, M)>);
+
+impl<P: Position2, M: PathMesh> Plugin for MapNavDebugPlugin<P, M> {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<NavGizmos>()
+            .add_systems(Update, (draw_navmeshes::<M>, draw_paths::<P, M>));
+    }
+}
+
+impl<P: Position2, M: PathMesh> Default for MapNavDebugPlugin<P, M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Gizmo config group for [`MapNavDebugPlugin`]. Toggle all of its drawing at runtime through
+/// the `enabled` field of its `GizmoConfig`, which you can get from Bevy's `GizmoConfigStore`.
+/// Each clearance is drawn in its own color.
+#[derive(Clone, Debug, Reflect, GizmoConfigGroup)]
+pub struct NavGizmos {
+    /// Whether to draw navmeshes. Each mesh's nodes are outlined faintly, and its boundary
+    /// edges are outlined brightly.
+    pub meshes: bool,
+    /// Clearances of the navmeshes to draw. Draws the same navmesh that [`Navmeshes::mesh`]
+    /// returns for each clearance. If `None`, every navmesh is drawn.
+    pub clearances: Option<Vec<f32>>,
+    /// Whether to draw [`Pathfind`] paths
+    pub paths: bool,
+}
+
+impl Default for NavGizmos {
+    fn default() -> Self {
+        Self {
+            meshes: true,
+            clearances: None,
+            paths: true,
+        }
+    }
+}
+
+// Lines of a navmesh's node outlines and boundary edges
+struct MeshLines {
+    clearance: f32,
+    sides: Vec<[Vec2; 2]>,
+    boundary: Vec<[Vec2; 2]>,
+}
+
+fn draw_navmeshes<M: PathMesh>(
+    mut gizmos: Gizmos<NavGizmos>,
+    maps: Query<(Entity, Ref<Navmeshes<M>>)>,
+    mut cache: Local<EntityHashMap<Vec<MeshLines>>>,
+) {
+    let config = gizmos.config_ext.clone();
+    if !config.meshes {
+        return;
+    }
+
+    cache.retain(|&map, _| maps.contains(map));
+    for (map, navmeshes) in &maps {
+        if navmeshes.is_changed() || !cache.contains_key(&map) {
+            cache.insert(map, mesh_lines(&navmeshes));
+        }
+
+        let lines = &cache[&map];
+        for (mesh, mesh_lines) in lines.iter().enumerate() {
+            if !selected(config.clearances.as_deref(), lines, mesh) {
+                continue;
+            }
+
+            let color = clearance_color(mesh);
+            for &[start, end] in &mesh_lines.sides {
+                gizmos.line_2d(start, end, color.with_alpha(0.2));
+            }
+            for &[start, end] in &mesh_lines.boundary {
+                gizmos.line_2d(start, end, color);
+            }
+        }
+    }
+}
+
+// Whether the mesh at the given index is the one that `Navmeshes::mesh` returns for any of the
+// clearances
+fn selected(clearances: Option<&[f32]>, lines: &[MeshLines], mesh: usize) -> bool {
+    clearances.is_none_or(|clearances| {
+        clearances.iter().any(|&clearance| {
+            lines[mesh].clearance >= clearance
+                && (mesh == 0 || lines[mesh - 1].clearance < clearance)
+        })
+    })
+}
+
+fn mesh_lines<M: PathMesh>(navmeshes: &Navmeshes<M>) -> Vec<MeshLines> {
+    (0..navmeshes.mesh_count())
+        .filter_map(|mesh| {
+            let polygons = navmeshes.mesh_at(mesh)?.polygons();
+            Some(MeshLines {
+                clearance: navmeshes.clearance_at(mesh)?,
+                sides: polygons
+                    .iter()
+                    .flat_map(|polygon| {
+                        polygon
+                            .iter()
+                            .enumerate()
+                            .map(|(index, &start)| [start, polygon[(index + 1) % polygon.len()]])
+                    })
+                    .collect(),
+                boundary: boundary_edges(&polygons),
+            })
+        })
+        .collect()
+}
+
+// Color of the navmesh at the given index, spread around the color wheel
+fn clearance_color(mesh: usize) -> Color {
+    Hsla::hsl((mesh as f32 * 137.5) % 360., 0.8, 0.6).into()
+}
+
+fn draw_paths<P: Position2, M: PathMesh>(
+    mut gizmos: Gizmos<NavGizmos>,
+    pathfinds: Query<(&P, &Pathfind)>,
+    maps: Query<(), With<Navmeshes<M>>>,
+) {
+    if !gizmos.config_ext.paths {
+        return;
+    }
+
+    for (position, pathfind) in &pathfinds {
+        // Paths on other maps are drawn by the plugin for their `PathMesh`
+        if pathfind.path.is_empty() || !maps.contains(pathfind.map) {
+            continue;
+        }
+
+        gizmos.linestrip_2d(
+            [position.get()]
+                .into_iter()
+                .chain(pathfind.path.iter().copied()),
+            Color::WHITE,
+        );
+        for &waypoint in &pathfind.path {
+            gizmos.circle_2d(waypoint, pathfind.radius.max(1.), Color::WHITE);
+        }
+
+        // Waypoints that haven't been refined yet
+        if let Some(&end) = pathfind.path.back() {
+            gizmos.linestrip_2d(
+                [end]
+                    .into_iter()
+                    .chain(pathfind.coarse_path.iter().copied()),
+                Color::WHITE.with_alpha(0.3),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(clearances: &[f32]) -> Vec<MeshLines> {
+        clearances
+            .iter()
+            .map(|&clearance| MeshLines {
+                clearance,
+                sides: Vec::new(),
+                boundary: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn drawn(clearances: Option<&[f32]>, lines: &[MeshLines]) -> Vec<usize> {
+        (0..lines.len())
+            .filter(|&mesh| selected(clearances, lines, mesh))
+            .collect()
+    }
+
+    #[test]
+    fn every_mesh_is_drawn_without_clearances() {
+        assert_eq!(drawn(None, &lines(&[0., 0.5, 1.])), [0, 1, 2]);
+        assert!(drawn(Some(&[]), &lines(&[0., 0.5, 1.])).is_empty());
+    }
+
+    #[test]
+    fn the_mesh_for_each_clearance_is_drawn() {
+        let lines = lines(&[0., 0.5, 1.]);
+        // Exact clearances
+        assert_eq!(drawn(Some(&[0.5]), &lines), [1]);
+        assert_eq!(drawn(Some(&[0., 1.]), &lines), [0, 2]);
+        // Clearances between meshes draw the next larger one
+        assert_eq!(drawn(Some(&[0.25]), &lines), [1]);
+        assert_eq!(drawn(Some(&[0.1, 0.4]), &lines), [1]);
+        assert_eq!(drawn(Some(&[0.75, -1.]), &lines), [0, 2]);
+        // No mesh has enough clearance
+        assert!(drawn(Some(&[1.5]), &lines).is_empty());
+    }
+}
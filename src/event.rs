@@ -0,0 +1,88 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::prelude::*;
+
+/// Triggered on a navigator when a path is found for its [`Pathfind`]
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct PathFound;
+
+/// Triggered on a navigator when a path cannot be found for its [`Pathfind`], or it cannot
+/// follow its [`FollowFlow`]. Also triggered on a [`Flow`]'s entity when its field cannot
+/// be generated.
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct PathFailed(pub PathError);
+
+/// Triggered on a navigator when it reaches a waypoint of its path
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct WaypointReached {
+    /// Waypoint that was reached
+    pub waypoint: Vec2,
+}
+
+/// Triggered on a navigator when it has navigated to its destination
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct Arrived;
+
+/// Triggered on a navigator with [`VelocityNav`] when it ends up too far from where it was
+/// expected to move, such as when its physics body is pushed. Its path is found again.
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct Drifted {
+    /// How far the navigator is from where it was expected to be
+    pub distance: f32,
+}
+
+/// Triggered on a navigator when its [`StuckDetection`] finds that it's stuck
+#[derive(Clone, Copy, Debug, Event, Reflect)]
+pub struct Stuck {
+    /// How far the navigator moved during the last window
+    pub progress: f32,
+}
+
+/// Why a path could not be found or followed
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum PathError {
+    /// The map entity does not exist, or no [`MapNavPlugin`]'s `PathMesh` has [`Navmeshes`]
+    /// on it
+    MissingMap(Entity),
+    /// The map has no navmesh with at least this much clearance
+    MissingMesh(f32),
+    /// A target entity does not have a position
+    MissingTarget(Entity),
+    /// The target has no positions, such as an empty [`PathTarget::Points`]
+    NoTargets,
+    /// The navigator is further from the navmesh than the navmesh's clearance
+    StartOffMesh,
+    /// None of the target's positions are on the navmesh, within the navmesh's clearance
+    TargetOffMesh,
+    /// No route leads to the target
+    NoRoute,
+    /// The target is not supported here, such as a [`PathTarget::Flee`] on a [`Flow`]
+    UnsupportedTarget,
+    /// The navigator got stuck following the path, and gave up
+    Stuck,
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMap(map) => write!(f, "map {map} does not exist or has no navmeshes"),
+            Self::MissingMesh(clearance) => {
+                write!(f, "missing navmesh with clearance of at least {clearance}")
+            }
+            Self::MissingTarget(target) => {
+                write!(f, "target {target} does not have a position")
+            }
+            Self::NoTargets => write!(f, "the target has no positions"),
+            Self::StartOffMesh => write!(f, "the navigator is not on the navmesh"),
+            Self::TargetOffMesh => write!(f, "the target is not on the navmesh"),
+            Self::NoRoute => write!(f, "no valid path was found"),
+            Self::UnsupportedTarget => write!(f, "the target is not supported here"),
+            Self::Stuck => write!(f, "the navigator got stuck"),
+        }
+    }
+}
+
+impl Error for PathError {}
@@ -0,0 +1,397 @@
+//! Drawing navmeshes to SVG or PNG, for bug reports and headless debugging
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use glam::{UVec2, Vec2};
+
+use crate::mesh::{boundary_edges, Navability, PathMesh};
+
+const BACKGROUND: [u8; 4] = [255, 255, 255, 255];
+const SOLID: [u8; 4] = [64, 64, 64, 255];
+const GRID: [u8; 4] = [208, 208, 208, 255];
+const NODE: [u8; 4] = [96, 160, 224, 96];
+const SIDE: [u8; 4] = [96, 160, 224, 255];
+const BOUNDARY: [u8; 4] = [16, 64, 160, 255];
+const PATH: [u8; 4] = [224, 32, 32, 255];
+
+/// A drawing of a navmesh, with an optional path and tile grid. Write it as SVG
+/// with [`MeshDrawing::write_svg`], or as PNG with `MeshDrawing::write_png` and
+/// the `png` feature.
+#[derive(Clone, Debug)]
+pub struct MeshDrawing {
+    /// Size of the tilemap in tiles
+    pub map_size: UVec2,
+    /// Size of each tile
+    pub tile_size: Vec2,
+    /// Outlines of the navmesh's nodes, from [`PathMesh::polygons`]
+    pub polygons: Vec<Vec<Vec2>>,
+    /// Path to draw over the navmesh. If empty, no path is drawn.
+    pub path: Vec<Vec2>,
+    /// Navability of each tile, row by row, to draw under the navmesh. If empty, no tiles
+    /// are drawn.
+    pub tiles: Vec<Navability>,
+    /// Pixels per unit of distance
+    pub scale: f32,
+    /// Whether y points up, like in Bevy. Otherwise, tile row 0 is drawn at the top.
+    pub y_up: bool,
+}
+
+impl MeshDrawing {
+    /// Create a `MeshDrawing` of the given mesh, with 16 pixels across each tile
+    pub fn new(map_size: UVec2, tile_size: Vec2, mesh: &impl PathMesh) -> Self {
+        Self {
+            map_size,
+            tile_size,
+            polygons: mesh.polygons(),
+            path: Vec::new(),
+            tiles: Vec::new(),
+            scale: 16. / tile_size.max_element(),
+            y_up: true,
+        }
+    }
+
+    /// Draw the given path over the navmesh
+    pub fn with_path(mut self, path: impl IntoIterator<Item = Vec2>) -> Self {
+        self.path = path.into_iter().collect();
+        self
+    }
+
+    /// Draw the tilemap under the navmesh. The input to `navability` is a tile's position.
+    pub fn with_tiles(mut self, navability: impl Fn(UVec2) -> Navability) -> Self {
+        self.tiles = (0..self.map_size.y)
+            .flat_map(|y| (0..self.map_size.x).map(move |x| UVec2::new(x, y)))
+            .map(navability)
+            .collect();
+        self
+    }
+
+    /// Size of the drawing in pixels
+    pub fn size(&self) -> UVec2 {
+        (self.map_size.as_vec2() * self.tile_size * self.scale)
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
+
+    /// Render the drawing as SVG
+    pub fn svg(&self) -> String {
+        let size = self.size();
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            size.x, size.y,
+        );
+        svg.push('\n');
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="{}"/>"#,
+            size.x,
+            size.y,
+            hex(BACKGROUND),
+        );
+
+        for (tile, corners) in self.tile_squares() {
+            let fill = match tile {
+                Navability::Navable => "none".to_string(),
+                Navability::Solid => hex(SOLID),
+            };
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{fill}" stroke="{}" stroke-width="1"/>"#,
+                self.points(&corners),
+                hex(GRID),
+            );
+        }
+
+        for polygon in &self.polygons {
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}" fill-opacity="{:.3}" stroke="{}" stroke-width="1"/>"#,
+                self.points(polygon),
+                hex(NODE),
+                NODE[3] as f32 / 255.,
+                hex(SIDE),
+            );
+        }
+
+        for [start, end] in boundary_edges(&self.polygons) {
+            let (start, end) = (self.pixel(start), self.pixel(end));
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="2"/>"#,
+                start.x,
+                start.y,
+                end.x,
+                end.y,
+                hex(BOUNDARY),
+            );
+        }
+
+        if !self.path.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                self.points(&self.path),
+                hex(PATH),
+            );
+            for &waypoint in &self.path {
+                let waypoint = self.pixel(waypoint);
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="3" fill="{}"/>"#,
+                    waypoint.x,
+                    waypoint.y,
+                    hex(PATH),
+                );
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Write the drawing as SVG
+    pub fn write_svg(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(self.svg().as_bytes())
+    }
+
+    /// Render the drawing as 8-bit RGBA pixels, row by row from the top. The image has
+    /// the size from [`MeshDrawing::size`].
+    pub fn rgba(&self) -> Vec<u8> {
+        let mut canvas = Canvas::new(self.size());
+
+        for (tile, corners) in self.tile_squares() {
+            let corners = corners.map(|corner| self.pixel(corner));
+            if tile == Navability::Solid {
+                canvas.fill(&corners, SOLID);
+            }
+            canvas.outline(&corners, 1., GRID);
+        }
+
+        for polygon in &self.polygons {
+            let polygon = polygon
+                .iter()
+                .map(|&point| self.pixel(point))
+                .collect::<Vec<_>>();
+            canvas.fill(&polygon, NODE);
+            canvas.outline(&polygon, 1., SIDE);
+        }
+
+        for [start, end] in boundary_edges(&self.polygons) {
+            canvas.line(self.pixel(start), self.pixel(end), 2., BOUNDARY);
+        }
+
+        for waypoints in self.path.windows(2) {
+            canvas.line(self.pixel(waypoints[0]), self.pixel(waypoints[1]), 2., PATH);
+        }
+        for &waypoint in &self.path {
+            canvas.line(self.pixel(waypoint), self.pixel(waypoint), 6., PATH);
+        }
+
+        canvas.pixels
+    }
+
+    /// Write the drawing as PNG
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, writer: impl Write) -> Result<(), png::EncodingError> {
+        let size = self.size();
+        let mut encoder = png::Encoder::new(writer, size.x, size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgba())
+    }
+
+    // Navability and corners of each tile, if the tiles are drawn
+    fn tile_squares(&self) -> impl Iterator<Item = (Navability, [Vec2; 4])> + '_ {
+        self.tiles.iter().enumerate().map(|(index, &tile)| {
+            let index = index as u32;
+            let min = UVec2::new(index % self.map_size.x, index / self.map_size.x).as_vec2()
+                * self.tile_size;
+            let max = min + self.tile_size;
+            (
+                tile,
+                [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+            )
+        })
+    }
+
+    // Position of the given point in the drawing, in pixels
+    fn pixel(&self, point: Vec2) -> Vec2 {
+        let pixel = point * self.scale;
+        match self.y_up {
+            true => Vec2::new(pixel.x, self.size().y as f32 - pixel.y),
+            false => pixel,
+        }
+    }
+
+    // SVG `points` attribute of the given points
+    fn points(&self, points: &[Vec2]) -> String {
+        points
+            .iter()
+            .map(|&point| {
+                let pixel = self.pixel(point);
+                format!("{},{}", pixel.x, pixel.y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn hex([r, g, b, _]: [u8; 4]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// RGBA pixels to rasterize onto
+struct Canvas {
+    size: UVec2,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            pixels: BACKGROUND.repeat((size.x * size.y) as usize),
+        }
+    }
+
+    // Blends the color onto the pixel, if it's in bounds
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 4]) {
+        if x < 0 || y < 0 || x >= self.size.x as i64 || y >= self.size.y as i64 {
+            return;
+        }
+
+        let index = (y as usize * self.size.x as usize + x as usize) * 4;
+        let alpha = color[3] as u32;
+        for (old, &new) in self.pixels[index..index + 3].iter_mut().zip(&color) {
+            *old = ((new as u32 * alpha + *old as u32 * (255 - alpha)) / 255) as u8;
+        }
+    }
+
+    // Fills a convex polygon, such as a triangle or tile
+    fn fill(&mut self, polygon: &[Vec2], color: [u8; 4]) {
+        if polygon.len() < 3 {
+            return;
+        }
+
+        let min = polygon.iter().copied().reduce(Vec2::min).unwrap().floor();
+        let max = polygon.iter().copied().reduce(Vec2::max).unwrap().ceil();
+        for y in min.y as i64..max.y as i64 {
+            for x in min.x as i64..max.x as i64 {
+                let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                // The center is inside if it's on the same side of every edge
+                let mut sides = polygon.iter().enumerate().map(|(index, &start)| {
+                    let end = polygon[(index + 1) % polygon.len()];
+                    (end - start).perp_dot(center - start)
+                });
+                let inside = sides.clone().all(|side| side >= 0.) || sides.all(|side| side <= 0.);
+                if inside {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn outline(&mut self, polygon: &[Vec2], width: f32, color: [u8; 4]) {
+        for (index, &start) in polygon.iter().enumerate() {
+            self.line(start, polygon[(index + 1) % polygon.len()], width, color);
+        }
+    }
+
+    // Draws a line by stamping squares along it. Overlapping stamps are only drawn correctly
+    // with opaque colors.
+    fn line(&mut self, start: Vec2, end: Vec2, width: f32, color: [u8; 4]) {
+        let steps = (start.distance(end) * 2.).ceil().max(1.) as u32;
+        let radius = (width / 2.).max(0.5);
+        for step in 0..=steps {
+            let point = start.lerp(end, step as f32 / steps as f32);
+            let min = (point - radius).round();
+            let max = (point + radius).round();
+            for y in min.y as i64..max.y as i64 {
+                for x in min.x as i64..max.x as i64 {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{GridSettings, TileGrid};
+
+    // 4x2 map with a solid tile in the top right, and a path along the bottom row
+    fn drawing(y_up: bool) -> MeshDrawing {
+        let solid = |tile: UVec2| match tile == UVec2::new(3, 1) {
+            true => Navability::Solid,
+            false => Navability::Navable,
+        };
+        let grid = TileGrid::generate(
+            UVec2::new(4, 2),
+            Vec2::ONE,
+            solid,
+            0.,
+            GridSettings::default(),
+        );
+        let mut drawing = MeshDrawing::new(UVec2::new(4, 2), Vec2::ONE, &grid)
+            .with_tiles(solid)
+            .with_path([Vec2::new(0.5, 0.5), Vec2::new(2.5, 0.5)]);
+        drawing.y_up = y_up;
+        drawing
+    }
+
+    fn pixel(drawing: &MeshDrawing, rgba: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * drawing.size().x + x) * 4) as usize;
+        rgba[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn svgs_have_the_tiles_mesh_and_path() {
+        for (y_up, path, solid) in [
+            (true, "8,24 40,24", "48,16 64,16 64,0 48,0"),
+            (false, "8,8 40,8", "48,16 64,16 64,32 48,32"),
+        ] {
+            let svg = drawing(y_up).svg();
+            assert!(svg.contains(r#"width="64" height="32""#));
+            // A square for each tile, and one for each open tile's node
+            assert_eq!(svg.matches("<polygon").count(), 8 + 7);
+            assert!(svg.contains(&format!(r##"<polygon points="{solid}" fill="#404040""##)));
+            assert_eq!(svg.matches("#404040").count(), 1);
+            assert!(svg.contains(&format!(r#"<polyline points="{path}""#)));
+            assert_eq!(svg.matches("<circle").count(), 2);
+        }
+    }
+
+    #[test]
+    fn pixels_have_the_tiles_mesh_and_path() {
+        // Node color blended over the background
+        let node = [195, 219, 243, 255];
+        for (y_up, top, bottom) in [(true, 8, 24), (false, 24, 8)] {
+            let drawing = drawing(y_up);
+            let rgba = drawing.rgba();
+            assert_eq!(rgba.len(), 64 * 32 * 4);
+            assert_eq!(pixel(&drawing, &rgba, 56, top), SOLID);
+            assert_eq!(pixel(&drawing, &rgba, 24, top), node);
+            assert_eq!(pixel(&drawing, &rgba, 56, bottom), node);
+            assert_eq!(pixel(&drawing, &rgba, 24, bottom), PATH);
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn pngs_have_the_pixels() {
+        let drawing = drawing(true);
+        let mut image = Vec::new();
+        drawing.write_png(&mut image).unwrap();
+
+        let mut reader = png::Decoder::new(&image[..]).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (64, 32));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(pixels, drawing.rgba());
+    }
+}
@@ -0,0 +1,236 @@
+use std::{collections::VecDeque, time::Duration};
+
+use navmesh::NavQuery;
+
+use crate::{
+    graph::FlowField,
+    mesh::PathMesh,
+    nav::{direction, go_to, length, node, repath_due, travel, NavStep, OtherMaps, TOLERANCE},
+    prelude::*,
+};
+
+pub(crate) fn plug<P: Position2, M: PathMesh>(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            generate_flows::<P, M>.in_set(NavStep::Path),
+            follow_flows::<P, M>.in_set(NavStep::Nav),
+        ),
+    );
+}
+
+/// Add this component to an entity to generate a flow field toward a target. Navigators with
+/// [`Nav`] and [`FollowFlow`] follow the field, so many navigators heading to the same target
+/// share one search.
+#[derive(Clone, Component, Debug)]
+pub struct Flow {
+    /// Tilemap with the [`Navmeshes`] component
+    pub map: Entity,
+    /// Clearance radius of the navigators following this field
+    pub radius: f32,
+    /// How often to regenerate the field, if ever
+    pub repath_frequency: Option<Duration>,
+    /// Next time to regenerate the field
+    pub next_repath: Duration,
+    /// Target to flow toward. Navigators flow toward whichever of its positions is nearest.
+    pub target: PathTarget,
+    /// Generated flow field
+    pub field: Option<FlowField>,
+    /// Why the field could not be generated, if it couldn't
+    pub error: Option<PathError>,
+    /// Quality of querying a point on the navmesh
+    pub query: NavQuery,
+}
+
+impl Flow {
+    /// Create a `Flow`
+    pub fn new(
+        map: Entity,
+        radius: f32,
+        repath_frequency: Option<Duration>,
+        target: PathTarget,
+        query: NavQuery,
+    ) -> Self {
+        Self {
+            map,
+            radius,
+            repath_frequency,
+            next_repath: Duration::ZERO,
+            target,
+            field: None,
+            error: None,
+            query,
+        }
+    }
+}
+
+/// Add this component and [`Nav`] to your entity to have it follow the [`Flow`]
+/// on the contained entity, instead of a [`Pathfind`] path
+#[derive(Clone, Component, Copy, Debug, Reflect)]
+#[require(FlowPath)]
+pub struct FollowFlow(pub Entity);
+
+// How many waypoints ahead a navigator looks for one in sight, to skip the ones before it
+const LOOKAHEAD: usize = 8;
+
+// Waypoints that a navigator is following through a flow field, reused until it moves to another
+// node or the field changes
+#[derive(Clone, Component, Debug, Default)]
+struct FlowPath {
+    node: Option<usize>,
+    path: VecDeque<Vec2>,
+}
+
+fn generate_flows<P: Position2, M: PathMesh>(
+    mut commands: Commands,
+    positions: Query<&P>,
+    mut flows: Query<(Entity, &mut Flow)>,
+    meshes: Query<Ref<Navmeshes<M>>>,
+    other_maps: OtherMaps<M>,
+    time: Res<Time>,
+) {
+    for (entity, mut flow) in &mut flows {
+        let navmeshes = meshes.get(flow.map);
+        if navmeshes.is_err() && other_maps.skip(flow.map) {
+            continue;
+        }
+
+        // Only mark the flow changed when its field is generated
+        let Flow {
+            repath_frequency,
+            next_repath,
+            ..
+        } = flow.bypass_change_detection();
+        let changed = navmeshes
+            .as_ref()
+            .is_ok_and(|navmeshes| navmeshes.is_changed());
+        if !repath_due(*repath_frequency, next_repath, time.elapsed()) && !changed {
+            continue;
+        }
+
+        let field = || -> Result<FlowField, PathError> {
+            let navmeshes = navmeshes.map_err(|_| PathError::MissingMap(flow.map))?;
+            let missing = PathError::MissingMesh(flow.radius);
+            let mesh = navmeshes.mesh(flow.radius).ok_or(missing)?;
+            let graph = navmeshes.graph(flow.radius).ok_or(missing)?;
+            let targets = flow
+                .target
+                .goal(&positions)?
+                .points(mesh, graph, flow.query)?;
+            let tolerance = navmeshes.clearance(flow.radius).unwrap_or(flow.radius) + TOLERANCE;
+
+            FlowField::generate(
+                mesh,
+                graph,
+                targets
+                    .into_iter()
+                    .map(|(_, target)| target)
+                    .filter(|&target| node(mesh, target, tolerance, flow.query).is_some()),
+                flow.query,
+            )
+            .ok_or(PathError::TargetOffMesh)
+        }();
+
+        if let Err(error) = field {
+            #[cfg(feature = "log")]
+            warn!("failed to generate flow field: {error}");
+            commands.trigger_targets(PathFailed(error), entity);
+        }
+        flow.error = field.as_ref().err().copied();
+        flow.field = field.ok();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn follow_flows<P: Position2, M: PathMesh>(
+    mut commands: Commands,
+    mut navs: Query<
+        (
+            Entity,
+            &mut P,
+            &FollowFlow,
+            &mut FlowPath,
+            &mut Nav,
+            Option<&mut VelocityNav>,
+        ),
+        Without<Pathfind>,
+    >,
+    flows: Query<Ref<Flow>>,
+    meshes: Query<&Navmeshes<M>>,
+    time: Res<Time>,
+) {
+    for (entity, mut position, &FollowFlow(flow), mut flow_path, mut nav, velocity_nav) in &mut navs
+    {
+        let Ok(flow) = flows.get(flow) else {
+            continue;
+        };
+        let Ok(navmeshes) = meshes.get(flow.map) else {
+            continue;
+        };
+        let (Some(field), Some(mesh)) = (&flow.field, navmeshes.mesh(flow.radius)) else {
+            continue;
+        };
+
+        let pos = position.get();
+        let was_done = nav.done;
+        let node = mesh.node(pos, flow.query);
+        if flow.is_changed() || node != flow_path.node || flow_path.path.is_empty() {
+            flow_path.node = node;
+            flow_path.path = match field.waypoints(mesh, pos, flow.query) {
+                Some(waypoints) => pull(mesh, pos, waypoints, flow.query),
+                None => VecDeque::new(),
+            };
+
+            if flow_path.path.is_empty() {
+                flow_path.node = None;
+                nav.done = true;
+                nav.velocity = Vec2::ZERO;
+                if !was_done {
+                    commands.trigger_targets(PathFailed(PathError::NoRoute), entity);
+                }
+                #[cfg(feature = "state")]
+                commands.entity(entity).insert(Done::Failure);
+                continue;
+            }
+        }
+
+        let path = &mut flow_path.path;
+        let remaining = length(pos, path.iter().copied());
+        let direction = direction(pos, path.iter().copied());
+        let delta = time.delta_secs();
+        let (travel_dist, arrived) = nav.step(remaining, direction, delta);
+
+        let start = pos;
+        let pos = travel(pos, travel_dist, path, |waypoint| {
+            commands.trigger_targets(WaypointReached { waypoint }, entity)
+        });
+        nav.moved(start, pos, delta);
+        nav.done = arrived || path.is_empty();
+        if nav.done && !was_done {
+            commands.trigger_targets(Arrived, entity);
+        }
+        #[cfg(feature = "state")]
+        if nav.done {
+            commands.entity(entity).insert(Done::Success);
+        }
+
+        go_to(&mut position, velocity_nav, pos);
+    }
+}
+
+// Skips to the furthest of the next few waypoints in line of sight, so navigators head straight
+// there instead of zig-zagging between portals
+fn pull<M: PathMesh>(
+    mesh: &M,
+    pos: Vec2,
+    waypoints: impl Iterator<Item = Vec2>,
+    query: NavQuery,
+) -> VecDeque<Vec2> {
+    let mut path = waypoints.collect::<VecDeque<_>>();
+    let visible = (1..path.len().min(LOOKAHEAD + 1))
+        .take_while(|&index| mesh.line_of_sight(pos, path[index], query))
+        .last()
+        .unwrap_or(0);
+    path.drain(..visible);
+    path
+}
@@ -0,0 +1,461 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy_ecs::query::QueryEntityError;
+
+use crate::{
+    mesh::PathMesh,
+    nav::{generate_paths, NavStep, PathTask},
+    prelude::*,
+};
+
+pub(crate) fn plug<P: Position2, M: PathMesh>(app: &mut App) {
+    app.add_observer(plan_on::<PathFound, P, M>)
+        .add_observer(plan_on::<WaypointReached, P, M>)
+        .add_systems(
+            Update,
+            (
+                lead_formations::<M>.before(generate_paths::<P, M>),
+                plan_on_change::<P, M>.after(generate_paths::<P, M>),
+            )
+                .in_set(NavStep::Path),
+        );
+}
+
+/// Add this component to a navigator with [`Pathfind`] to have it lead a group in formation.
+/// The leader's path is found with the clearance of its widest member, and each member follows
+/// it from its slot in the formation, projected onto the navmesh. Members need [`Pathfind`] and
+/// [`Nav`], but their paths are set by the formation instead of being found themselves. Their
+/// paths are planned again each time the leader reaches a waypoint, so members with shorter
+/// paths don't get ahead. While in `members`, they have a [`FormationMember`] component, and
+/// their [`Pathfind::target`] is replaced with their slot at the end of the leader's path.
+/// Once they leave, they find their own paths to that target again, so set it if they should go
+/// somewhere else.
+/// The leader may be an entity that is only there to lead, or one of the group, in which case
+/// leave it out of `members`, and pick a shape that leaves its slot empty, such as
+/// [`FormationShape::Wedge`].
+#[derive(Clone, Component, Debug, Reflect)]
+pub struct Formation {
+    /// Navigators that follow the leader, in order of their slots
+    pub members: Vec<Entity>,
+    /// Arrangement of the slots
+    pub shape: FormationShape,
+    /// Distance between neighboring slots. Not used by [`FormationShape::Custom`].
+    pub spacing: f32,
+}
+
+impl Formation {
+    /// Create a `Formation`
+    pub fn new(members: Vec<Entity>, shape: FormationShape, spacing: f32) -> Self {
+        Self {
+            members,
+            shape,
+            spacing,
+        }
+    }
+
+    /// Offsets of the members' slots from the leader, where positive x is the direction
+    /// the leader is heading, and positive y is to its left
+    pub fn offsets(&self) -> Vec<Vec2> {
+        let count = self.members.len();
+        let spacing = self.spacing;
+        // Position of `index` out of `count`, relative to their middle
+        let centered = |index: usize, count: usize| index as f32 - (count - 1) as f32 / 2.;
+
+        (0..count)
+            .map(|index| match &self.shape {
+                FormationShape::Line => Vec2::new(0., -centered(index, count) * spacing),
+                FormationShape::Box => {
+                    let columns = (count as f32).sqrt().ceil() as usize;
+                    let rows = count.div_ceil(columns);
+                    Vec2::new(
+                        -centered(index / columns, rows),
+                        -centered(index % columns, columns),
+                    ) * spacing
+                }
+                FormationShape::Wedge => {
+                    let row = (index / 2 + 1) as f32;
+                    let side = match index % 2 {
+                        0 => 1.,
+                        _ => -1.,
+                    };
+                    Vec2::new(-row, side * row) * spacing
+                }
+                FormationShape::Custom(offsets) => offsets.get(index).copied().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Added to the members of a [`Formation`], and removed when they leave it. Members don't find
+/// their own paths, even when their [`RepathPolicy`] or `repath_frequency` says to.
+#[derive(Clone, Component, Copy, Debug, Reflect)]
+pub struct FormationMember {
+    /// Leader of the formation
+    pub leader: Entity,
+}
+
+/// Arrangement of a [`Formation`]'s slots
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub enum FormationShape {
+    /// Side by side, across the direction of travel, centered on the leader
+    Line,
+    /// Grid with about as many rows as columns, centered on the leader
+    Box,
+    /// V shape, with the leader at its point, and members alternating between its sides
+    Wedge,
+    /// Offsets from the leader, where positive x is the direction the leader is heading,
+    /// and positive y is to its left. Members without an offset are placed on the leader.
+    Custom(Vec<Vec2>),
+}
+
+// Finds leaders' paths with their widest member's clearance, and marks their members, so they
+// don't find their own paths
+fn lead_formations<M: PathMesh>(
+    mut commands: Commands,
+    leaders: Query<(Entity, &Formation)>,
+    members: Query<(Entity, &FormationMember)>,
+    mut pathfinds: Query<&mut Pathfind>,
+    meshes: Query<(), With<Navmeshes<M>>>,
+) {
+    for (member, &FormationMember { leader }) in &members {
+        let Ok((_, formation)) = leaders.get(leader) else {
+            leave(&mut commands, member, &mut pathfinds);
+            continue;
+        };
+
+        if !formation.members.contains(&member) {
+            leave(&mut commands, member, &mut pathfinds);
+        }
+    }
+
+    for (leader, formation) in &leaders {
+        let mut radius = None::<f32>;
+        for &member in &formation.members {
+            let Ok(pathfind) = pathfinds.get(member) else {
+                continue;
+            };
+
+            if members.get(member).map(|(_, member)| member.leader) != Ok(leader) {
+                // Any path being found for the member is outdated
+                commands
+                    .entity(member)
+                    .try_remove::<PathTask>()
+                    .try_insert(FormationMember { leader });
+            }
+
+            radius = Some(radius.map_or(pathfind.radius, |radius| radius.max(pathfind.radius)));
+        }
+
+        let Ok(mut pathfind) = pathfinds.get_mut(leader) else {
+            continue;
+        };
+
+        if !meshes.contains(pathfind.map) {
+            continue;
+        }
+
+        if let Some(radius) = radius {
+            if pathfind.radius != radius {
+                pathfind.radius = radius;
+            }
+        }
+    }
+}
+
+// Lets a navigator that left its formation find its own path again
+fn leave(commands: &mut Commands, member: Entity, pathfinds: &mut Query<&mut Pathfind>) {
+    commands.entity(member).try_remove::<FormationMember>();
+    if let Ok(mut pathfind) = pathfinds.get_mut(member) {
+        pathfind.next_repath = Duration::ZERO;
+    }
+}
+
+// Plans when the leader finds a path, and as it reaches each waypoint
+fn plan_on<E: Event, P: Position2, M: PathMesh>(
+    trigger: Trigger<E>,
+    mut commands: Commands,
+    leaders: Query<(&P, &Formation, Option<&Nav>)>,
+    positions: Query<&P>,
+    mut navs: Query<(&mut Pathfind, Option<&mut Nav>), Without<Formation>>,
+    leader_pathfinds: Query<&Pathfind, With<Formation>>,
+    meshes: Query<&Navmeshes<M>>,
+) {
+    plan(
+        trigger.target(),
+        &mut commands,
+        &leaders,
+        &positions,
+        &mut navs,
+        &leader_pathfinds,
+        &meshes,
+    );
+}
+
+fn plan_on_change<P: Position2, M: PathMesh>(
+    mut commands: Commands,
+    changed: Query<Entity, Changed<Formation>>,
+    leaders: Query<(&P, &Formation, Option<&Nav>)>,
+    positions: Query<&P>,
+    mut navs: Query<(&mut Pathfind, Option<&mut Nav>), Without<Formation>>,
+    leader_pathfinds: Query<&Pathfind, With<Formation>>,
+    meshes: Query<&Navmeshes<M>>,
+) {
+    for leader in &changed {
+        plan(
+            leader,
+            &mut commands,
+            &leaders,
+            &positions,
+            &mut navs,
+            &leader_pathfinds,
+            &meshes,
+        );
+    }
+}
+
+// Sets the leader's members' paths to follow its path from their slots
+fn plan<P: Position2, M: PathMesh>(
+    leader: Entity,
+    commands: &mut Commands,
+    leaders: &Query<(&P, &Formation, Option<&Nav>)>,
+    positions: &Query<&P>,
+    navs: &mut Query<(&mut Pathfind, Option<&mut Nav>), Without<Formation>>,
+    leader_pathfinds: &Query<&Pathfind, With<Formation>>,
+    meshes: &Query<&Navmeshes<M>>,
+) {
+    let Ok((position, formation, leader_nav)) = leaders.get(leader) else {
+        return;
+    };
+    let Ok(leader_pathfind) = leader_pathfinds.get(leader) else {
+        return;
+    };
+    if let Err(QueryEntityError::QueryDoesNotMatch(..)) = meshes.get(leader_pathfind.map) {
+        // The map is handled by another `PathMesh`
+        return;
+    }
+
+    // The leader's route, and which way it's heading at each point
+    let facing = leader_nav.map_or(Vec2::X, |nav| *nav.facing);
+    let mut route = vec![(position.get(), facing)];
+    for waypoint in leader_pathfind
+        .path
+        .iter()
+        .chain(&leader_pathfind.coarse_path)
+        .copied()
+    {
+        let &(prev, heading) = route.last().unwrap();
+        route.push((
+            waypoint,
+            (waypoint - prev).try_normalize().unwrap_or(heading),
+        ));
+    }
+
+    // Start off heading toward the first waypoint
+    if let Some(&(_, heading)) = route.get(1) {
+        route[0].1 = heading;
+    }
+
+    for (&member, offset) in formation.members.iter().zip(formation.offsets()) {
+        let Ok((mut pathfind, nav)) = navs.get_mut(member) else {
+            continue;
+        };
+        let Ok(mesh) = meshes.get(pathfind.map) else {
+            continue;
+        };
+        let Some(mesh) = mesh.mesh(pathfind.radius) else {
+            continue;
+        };
+
+        let query = pathfind.query;
+        let slots = route
+            .iter()
+            .filter_map(|&(point, heading)| {
+                mesh.closest_point(
+                    point + heading * offset.x + heading.perp() * offset.y,
+                    query,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Find the way to the formation, and then keep to the slot. The slot may turn corners
+        // more sharply than the leader, so it's followed around obstacles too.
+        let path_mode = pathfind.path_mode;
+        let path = positions
+            .get(member)
+            .ok()
+            .zip(slots.first())
+            .and_then(|(position, &slot)| mesh.find_path(position.get(), slot, query, path_mode))
+            .map(|mut path| {
+                // Slots that can't be reached, such as across a wall from the last one,
+                // are skipped
+                for &slot in slots.iter().skip(1) {
+                    let from = path.last().copied().unwrap_or(slot);
+                    if let Some(leg) = mesh.find_path(from, slot, query, path_mode) {
+                        path.extend(leg.into_iter().skip(1));
+                    }
+                }
+
+                path
+            });
+        let Some(path) = path else {
+            pathfind.path.clear();
+            pathfind.coarse_path.clear();
+            pathfind.destination = None;
+            pathfind.previous_waypoint = None;
+            pathfind.error = Some(PathError::NoRoute);
+            commands.trigger_targets(PathFailed(PathError::NoRoute), member);
+
+            if let Some(mut nav) = nav {
+                nav.done = true;
+            }
+
+            #[cfg(feature = "state")]
+            commands.entity(member).insert(Done::Failure);
+
+            continue;
+        };
+
+        pathfind.destination = path.last().copied();
+        pathfind.previous_waypoint = path.first().copied();
+        pathfind.path = VecDeque::from(path);
+        pathfind.coarse_path.clear();
+        pathfind.target = PathTarget::Static(pathfind.destination.unwrap_or_default());
+        pathfind.chosen_target = None;
+        pathfind.pending = false;
+        pathfind.error = None;
+        commands.trigger_targets(PathFound, member);
+
+        if let Some(mut nav) = nav {
+            nav.done = pathfind.path.is_empty();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn offsets(shape: FormationShape, count: usize, spacing: f32) -> Vec<Vec2> {
+        Formation::new(vec![Entity::PLACEHOLDER; count], shape, spacing).offsets()
+    }
+
+    #[test]
+    fn slots_are_laid_out_by_shape() {
+        assert_eq!(
+            offsets(FormationShape::Line, 3, 2.),
+            [Vec2::new(0., 2.), Vec2::ZERO, Vec2::new(0., -2.)]
+        );
+        assert_eq!(
+            offsets(FormationShape::Box, 4, 1.),
+            [
+                Vec2::new(0.5, 0.5),
+                Vec2::new(0.5, -0.5),
+                Vec2::new(-0.5, 0.5),
+                Vec2::new(-0.5, -0.5),
+            ]
+        );
+        // The last row isn't full
+        assert_eq!(
+            offsets(FormationShape::Box, 3, 1.),
+            [
+                Vec2::new(0.5, 0.5),
+                Vec2::new(0.5, -0.5),
+                Vec2::new(-0.5, 0.5),
+            ]
+        );
+        assert_eq!(
+            offsets(FormationShape::Wedge, 3, 1.),
+            [Vec2::new(-1., 1.), Vec2::new(-1., -1.), Vec2::new(-2., 2.)]
+        );
+        assert_eq!(
+            offsets(FormationShape::Custom(vec![Vec2::X]), 2, 1.),
+            [Vec2::X, Vec2::ZERO]
+        );
+    }
+
+    // Spawns a leader at `from`, heading to `to`, with a line of members of each radius
+    fn formation(app: &mut App, map: Entity, from: Vec2, to: Vec2) -> (Entity, [Entity; 2]) {
+        let target = PathTarget::Static(to);
+        let members = [0., 0.25].map(|radius| {
+            testing::spawn(
+                app,
+                from,
+                (Nav::new(2.), testing::pathfind(map, radius, target.clone())),
+            )
+        });
+        let leader = testing::spawn(
+            app,
+            from,
+            (
+                Nav::new(2.),
+                testing::pathfind(map, 0., target),
+                Formation::new(members.to_vec(), FormationShape::Line, 4.),
+            ),
+        );
+
+        (leader, members)
+    }
+
+    #[test]
+    fn members_keep_to_slots_on_the_mesh() {
+        let mut app = testing::app();
+        let map = testing::map(&mut app, UVec2::splat(10), |_| false, [0., 0.25]);
+        let (leader, members) = formation(&mut app, map, Vec2::new(1.5, 1.), Vec2::new(8.5, 1.));
+
+        testing::run(&mut app, 2);
+        // The leader's path fits its widest member
+        assert_eq!(testing::get::<Pathfind>(&app, leader).radius, 0.25);
+        assert_eq!(
+            testing::get::<FormationMember>(&app, members[0]).leader,
+            leader
+        );
+
+        // The right slot ends up below the map, so it's moved onto the mesh
+        let pathfind = testing::get::<Pathfind>(&app, members[1]);
+        let slot = Vec2::new(8.5, 0.25);
+        assert!(pathfind.destination.unwrap().distance(slot) < 1e-3);
+        assert_eq!(
+            pathfind.target,
+            PathTarget::Static(pathfind.destination.unwrap())
+        );
+        assert!(
+            testing::get::<Pathfind>(&app, members[0])
+                .destination
+                .unwrap()
+                .distance(Vec2::new(8.5, 3.))
+                < 1e-3
+        );
+
+        testing::run(&mut app, 50);
+        assert!(testing::position(&app, members[1]).distance(slot) < 1e-3);
+    }
+
+    #[test]
+    fn members_are_planned_again_at_each_waypoint() {
+        let mut app = testing::app();
+        testing::record::<PathFound>(&mut app);
+        // Wall across the middle, with a gap at the top
+        let map = testing::map(
+            &mut app,
+            UVec2::splat(10),
+            |tile| tile.x == 5 && tile.y < 7,
+            [0., 0.25],
+        );
+        let (leader, members) = formation(&mut app, map, Vec2::new(1.5, 1.), Vec2::new(8.5, 1.));
+
+        testing::run(&mut app, 2);
+        let waypoints = testing::get::<Pathfind>(&app, leader).path.len();
+        assert!(waypoints >= 2);
+        testing::recorded::<PathFound>(&mut app, members[1]);
+
+        testing::run(&mut app, 100);
+        assert!(testing::get::<Nav>(&app, leader).done);
+        assert_eq!(
+            testing::recorded::<PathFound>(&mut app, members[1]).len(),
+            waypoints
+        );
+        let destination = testing::get::<Pathfind>(&app, members[1]).destination;
+        assert!(testing::position(&app, members[1]).distance(destination.unwrap()) < 1e-3);
+    }
+}
@@ -0,0 +1,336 @@
+//! Graphs of the nodes that paths travel through, and flow fields over them
+
+use std::collections::BinaryHeap;
+
+use glam::Vec2;
+use navmesh::NavQuery;
+
+use crate::{mesh::PathMesh, search::Frontier};
+
+/// A connection from one node of a [`NavGraph`] to another
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavEdge {
+    /// Node this edge leads to
+    pub to: usize,
+    /// Point to travel through to get to the other node
+    pub portal: Vec2,
+    /// Cost of traveling this edge
+    pub cost: f32,
+}
+
+/// Graph of the nodes, such as triangles or tiles, of a [`PathMesh`]. Used for searches
+/// that [`PathMesh::find_path`] can't do, such as flow fields.
+#[derive(Clone, Debug, Default)]
+pub struct NavGraph {
+    positions: Vec<Vec2>,
+    edges: Vec<Vec<NavEdge>>,
+}
+
+impl NavGraph {
+    /// Create a graph from each node's position and edges
+    pub fn new(nodes: impl IntoIterator<Item = (Vec2, Vec<NavEdge>)>) -> Self {
+        let (positions, edges) = nodes.into_iter().unzip();
+        Self { positions, edges }
+    }
+
+    /// Gets the number of nodes
+    pub fn node_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Gets the position of the given node
+    pub fn position(&self, node: usize) -> Vec2 {
+        self.positions[node]
+    }
+
+    /// Gets the edges leading out of the given node
+    pub fn edges(&self, node: usize) -> &[NavEdge] {
+        &self.edges[node]
+    }
+
+    /// Find the cheapest cost from any of the `sources` to every node, starting each source
+    /// at the given cost. Also returns the edge each node was reached through, which leads
+    /// back toward the sources. The graph's edges are assumed to be symmetric.
+    pub fn dijkstra(
+        &self,
+        sources: impl IntoIterator<Item = (usize, f32)>,
+    ) -> (Vec<f32>, Vec<Option<NavEdge>>) {
+        self.dijkstra_within(sources, f32::INFINITY)
+    }
+
+    /// Like [`NavGraph::dijkstra`], but stops searching at nodes that cost more than `budget`.
+    /// Those nodes have infinite cost.
+    pub fn dijkstra_within(
+        &self,
+        sources: impl IntoIterator<Item = (usize, f32)>,
+        budget: f32,
+    ) -> (Vec<f32>, Vec<Option<NavEdge>>) {
+        let mut costs = vec![f32::INFINITY; self.node_count()];
+        let mut parents = vec![None; self.node_count()];
+        let mut frontier = BinaryHeap::new();
+
+        for (node, cost) in sources {
+            if cost < costs[node] {
+                costs[node] = cost;
+                frontier.push(Frontier {
+                    priority: cost,
+                    index: node,
+                });
+            }
+        }
+
+        while let Some(Frontier { priority, index }) = frontier.pop() {
+            if priority > costs[index] {
+                continue;
+            }
+
+            for edge in &self.edges[index] {
+                let cost = priority + edge.cost;
+                if cost <= budget && cost < costs[edge.to] {
+                    costs[edge.to] = cost;
+                    parents[edge.to] = Some(NavEdge { to: index, ..*edge });
+                    frontier.push(Frontier {
+                        priority: cost,
+                        index: edge.to,
+                    });
+                }
+            }
+        }
+
+        (costs, parents)
+    }
+
+    /// Find which of the `goals` is cheapest to reach from `from`. Returns its index
+    /// in `goals`.
+    pub fn nearest(&self, from: usize, goals: &[usize]) -> Option<usize> {
+        let mut costs = vec![f32::INFINITY; self.node_count()];
+        let mut frontier = BinaryHeap::new();
+
+        costs[from] = 0.;
+        frontier.push(Frontier {
+            priority: 0.,
+            index: from,
+        });
+
+        while let Some(Frontier { priority, index }) = frontier.pop() {
+            if let Some(goal) = goals.iter().position(|&goal| goal == index) {
+                return Some(goal);
+            }
+
+            if priority > costs[index] {
+                continue;
+            }
+
+            for edge in &self.edges[index] {
+                let cost = priority + edge.cost;
+                if cost < costs[edge.to] {
+                    costs[edge.to] = cost;
+                    frontier.push(Frontier {
+                        priority: cost,
+                        index: edge.to,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Directions toward the nearest of some targets from every node of a [`PathMesh`].
+/// Generate one per set of targets, and share it between all navigators heading there.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    // Sorted by node
+    targets: Vec<(usize, Vec2)>,
+    costs: Vec<f32>,
+    next: Vec<Option<NavEdge>>,
+}
+
+impl FlowField {
+    /// Generate a flow field toward the nearest of the `targets`. `graph` should be the given
+    /// mesh's graph. Returns `None` if none of the targets are on the mesh.
+    pub fn generate(
+        mesh: &impl PathMesh,
+        graph: &NavGraph,
+        targets: impl IntoIterator<Item = Vec2>,
+        query: NavQuery,
+    ) -> Option<Self> {
+        let mut targets = targets
+            .into_iter()
+            .filter_map(|target| {
+                Some((
+                    mesh.node(target, query)?,
+                    mesh.closest_point(target, query)?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            return None;
+        }
+
+        targets.sort_by_key(|&(node, _)| node);
+        targets.dedup_by_key(|&mut (node, _)| node);
+        let (costs, next) = graph.dijkstra(targets.iter().map(|&(node, _)| (node, 0.)));
+
+        Some(Self {
+            targets,
+            costs,
+            next,
+        })
+    }
+
+    /// Gets the targets that this field flows toward, each paired with its node
+    pub fn targets(&self) -> &[(usize, Vec2)] {
+        &self.targets
+    }
+
+    /// Gets the approximate cost to travel from the given node to the nearest target.
+    /// Infinite if no target can be reached.
+    pub fn cost(&self, node: usize) -> f32 {
+        self.costs[node]
+    }
+
+    /// Gets the waypoints from `point` to the nearest target, ending at that target. Returns
+    /// `None` if no target can be reached from `point`.
+    pub fn waypoints<'a>(
+        &'a self,
+        mesh: &impl PathMesh,
+        point: Vec2,
+        query: NavQuery,
+    ) -> Option<impl Iterator<Item = Vec2> + 'a> {
+        let node = mesh.node(point, query)?;
+        self.costs[node].is_finite().then(|| {
+            let mut node = Some(node);
+            std::iter::from_fn(move || {
+                let current = node?;
+                match self.next[current] {
+                    Some(edge) => {
+                        node = Some(edge.to);
+                        Some(edge.portal)
+                    }
+                    None => {
+                        node = None;
+                        let target = self
+                            .targets
+                            .binary_search_by_key(&current, |&(node, _)| node)
+                            .ok()?;
+                        Some(self.targets[target].1)
+                    }
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec2;
+    use navmesh::NavPathMode;
+
+    use super::*;
+    use crate::{
+        grid::{GridSettings, TileGrid},
+        mesh::Navability,
+    };
+
+    // 6x4 map with a wall at x = 2 that has a gap at the top
+    fn grid() -> TileGrid {
+        TileGrid::generate(
+            UVec2::new(6, 4),
+            Vec2::ONE,
+            |tile| match tile.x == 2 && tile.y < 3 {
+                true => Navability::Solid,
+                false => Navability::Navable,
+            },
+            0.,
+            GridSettings::default(),
+        )
+    }
+
+    fn node(grid: &TileGrid, x: f32, y: f32) -> usize {
+        grid.node(Vec2::new(x, y), NavQuery::Accuracy).unwrap()
+    }
+
+    #[test]
+    fn dijkstra_matches_path_lengths() {
+        let grid = grid();
+        let graph = grid.graph();
+        let from = Vec2::new(0.5, 0.5);
+        let (costs, _) = graph.dijkstra([(node(&grid, from.x, from.y), 0.)]);
+
+        // Closed tiles have no edges
+        for (node, &cost) in costs.iter().enumerate() {
+            if graph.edges(node).is_empty() {
+                continue;
+            }
+
+            let to = graph.position(node);
+            match grid.find_path(from, to, NavQuery::Accuracy, NavPathMode::Accuracy) {
+                Some(path) => {
+                    let length = path
+                        .windows(2)
+                        .map(|waypoints| waypoints[0].distance(waypoints[1]))
+                        .sum::<f32>();
+                    assert!((cost - length).abs() < 1e-4);
+                }
+                None => assert_eq!(cost, f32::INFINITY),
+            }
+        }
+    }
+
+    #[test]
+    fn dijkstra_within_stops_at_the_budget() {
+        let grid = grid();
+        let graph = grid.graph();
+        let (costs, _) = graph.dijkstra_within([(node(&grid, 0.5, 0.5), 0.)], 2.);
+        assert_eq!(costs[node(&grid, 1.5, 1.5)], 2f32.sqrt());
+        assert_eq!(costs[node(&grid, 0.5, 3.5)], f32::INFINITY);
+        assert_eq!(costs[node(&grid, 5.5, 0.5)], f32::INFINITY);
+    }
+
+    #[test]
+    fn nearest_goal_is_cheapest_to_reach() {
+        let grid = grid();
+        let graph = grid.graph();
+        // The goal across the wall is closer in a straight line, but further to walk to
+        let goals = [node(&grid, 3.5, 0.5), node(&grid, 0.5, 3.5)];
+        assert_eq!(graph.nearest(node(&grid, 1.5, 0.5), &goals), Some(1));
+        assert_eq!(graph.nearest(node(&grid, 1.5, 0.5), &[]), None);
+    }
+
+    #[test]
+    fn flow_fields_lead_to_the_nearest_target() {
+        let grid = grid();
+        let graph = grid.graph();
+        let targets = [Vec2::new(5.5, 0.5), Vec2::new(0.5, 3.5)];
+        let field = FlowField::generate(&grid, &graph, targets, NavQuery::Accuracy).unwrap();
+        assert_eq!(field.targets().len(), 2);
+
+        for (from, target) in [
+            (Vec2::new(4.5, 0.5), targets[0]),
+            (Vec2::new(0.5, 0.5), targets[1]),
+        ] {
+            let waypoints = field
+                .waypoints(&grid, from, NavQuery::Accuracy)
+                .unwrap()
+                .collect::<Vec<_>>();
+            assert_eq!(waypoints.last(), Some(&target));
+
+            // Costs fall along the waypoints
+            let costs = waypoints
+                .iter()
+                .map(|&waypoint| field.cost(node(&grid, waypoint.x, waypoint.y)))
+                .collect::<Vec<_>>();
+            assert!(costs.windows(2).all(|costs| costs[1] < costs[0]));
+            assert_eq!(costs.last(), Some(&0.));
+        }
+    }
+
+    #[test]
+    fn flow_fields_need_a_target_on_the_mesh() {
+        let grid = grid();
+        let field = FlowField::generate(&grid, &grid.graph(), [], NavQuery::Accuracy);
+        assert!(field.is_none());
+    }
+}
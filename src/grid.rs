@@ -0,0 +1,589 @@
+//! Grid pathfinding over the raw tilemap, an alternative to navmeshes
+
+use std::collections::BinaryHeap;
+
+use glam::{IVec2, UVec2, Vec2};
+use navmesh::{NavPathMode, NavQuery};
+
+use crate::{
+    graph::{NavEdge, NavGraph},
+    mesh::{Navability, Navmeshes, PathMesh},
+    search::Frontier,
+};
+
+const DIRECTIONS: [IVec2; 8] = [
+    IVec2::X,
+    IVec2::NEG_X,
+    IVec2::Y,
+    IVec2::NEG_Y,
+    IVec2::ONE,
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::NEG_ONE,
+];
+
+/// Which neighboring tiles navigators can move to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Connectivity {
+    /// Orthogonally adjacent tiles
+    Four,
+    /// Orthogonally and diagonally adjacent tiles
+    Eight(CornerCutting),
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self::Eight(CornerCutting::Never)
+    }
+}
+
+/// When navigators may move diagonally past the corner of a tile they cannot stand on
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CornerCutting {
+    /// Both tiles beside the diagonal must be open
+    #[default]
+    Never,
+    /// At least one tile beside the diagonal must be open
+    IfEitherOpen,
+    /// Diagonal moves are always allowed, even between two closed tiles
+    Always,
+}
+
+/// Settings for how a [`TileGrid`] finds paths
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GridSettings {
+    /// Which neighboring tiles navigators can move to
+    pub connectivity: Connectivity,
+    /// Whether to use jump point search, which is usually faster on open maps. Only used with
+    /// `Connectivity::Eight(CornerCutting::Never)`. Other connectivities always use A*.
+    pub jump_points: bool,
+}
+
+/// A [`PathMesh`] that finds paths from tile to tile with A* or jump point search.
+/// Paths go through tile centers, so navigators move exactly tile-by-tile.
+/// Ignores `NavQuery` and `NavPathMode`.
+#[derive(Clone, Debug)]
+pub struct TileGrid {
+    map_size: UVec2,
+    tile_size: Vec2,
+    open: Vec<bool>,
+    settings: GridSettings,
+}
+
+impl TileGrid {
+    /// Generate a grid for your tilemap. The input to `navability` is a tile's position.
+    /// A tile is open if a square with radius `clearance` at its center only touches
+    /// navigable tiles.
+    pub fn generate(
+        map_size: UVec2,
+        tile_size: Vec2,
+        navability: impl Fn(UVec2) -> Navability,
+        clearance: f32,
+        settings: GridSettings,
+    ) -> Self {
+        let navability = (0..map_size.y)
+            .flat_map(|y| (0..map_size.x).map(move |x| UVec2::new(x, y)))
+            .map(navability)
+            .collect::<Vec<_>>();
+
+        let open = (0..map_size.y)
+            .flat_map(|y| (0..map_size.x).map(move |x| UVec2::new(x, y)))
+            .map(|tile| {
+                let center = (tile.as_vec2() + 0.5) * tile_size;
+                let min = ((center - clearance) / tile_size).floor().as_ivec2();
+                let max = ((center + clearance) / tile_size).ceil().as_ivec2() - 1;
+
+                min.cmpge(IVec2::ZERO).all()
+                    && max.cmplt(map_size.as_ivec2()).all()
+                    && (min.y..=max.y).all(|y| {
+                        (min.x..=max.x).all(|x| {
+                            navability[(y as u32 * map_size.x + x as u32) as usize]
+                                == Navability::Navable
+                        })
+                    })
+            })
+            .collect();
+
+        Self {
+            map_size,
+            tile_size,
+            open,
+            settings,
+        }
+    }
+
+    /// Gets the size of the map in tiles
+    pub fn map_size(&self) -> UVec2 {
+        self.map_size
+    }
+
+    /// Gets the size of each tile
+    pub fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    /// Gets the settings used to find paths
+    pub fn settings(&self) -> GridSettings {
+        self.settings
+    }
+
+    /// Whether navigators can stand on the given tile
+    pub fn is_open(&self, tile: UVec2) -> bool {
+        tile.cmplt(self.map_size).all() && self.open[self.index(tile.as_ivec2())]
+    }
+
+    /// Gets the tile that contains the given point, if it is on the map
+    pub fn tile(&self, point: Vec2) -> Option<UVec2> {
+        let tile = (point / self.tile_size).floor();
+        (tile.cmpge(Vec2::ZERO).all() && tile.cmplt(self.map_size.as_vec2()).all())
+            .then(|| tile.as_uvec2())
+    }
+
+    /// Gets the center of the given tile
+    pub fn tile_center(&self, tile: UVec2) -> Vec2 {
+        (tile.as_vec2() + 0.5) * self.tile_size
+    }
+
+    /// Gets the open tile closest to the given point
+    pub fn closest_tile(&self, point: Vec2) -> Option<UVec2> {
+        if let Some(tile) = self.tile(point).filter(|&tile| self.is_open(tile)) {
+            return Some(tile);
+        }
+
+        (0..self.open.len())
+            .filter(|&index| self.open[index])
+            .map(|index| self.tile_at(index).as_uvec2())
+            .min_by(|&tile1, &tile2| {
+                let dist1 = self.tile_center(tile1).distance_squared(point);
+                let dist2 = self.tile_center(tile2).distance_squared(point);
+                dist1.total_cmp(&dist2)
+            })
+    }
+
+    /// Find a path of tiles from `from` to `to`, including both
+    pub fn find_tile_path(&self, from: UVec2, to: UVec2) -> Option<Vec<UVec2>> {
+        if !self.is_open(from) || !self.is_open(to) {
+            return None;
+        }
+
+        let (from, to) = (from.as_ivec2(), to.as_ivec2());
+        let jump_points = self.settings.jump_points
+            && self.settings.connectivity == Connectivity::Eight(CornerCutting::Never);
+
+        let mut costs = vec![f32::INFINITY; self.open.len()];
+        let mut parents = vec![usize::MAX; self.open.len()];
+        let mut closed = vec![false; self.open.len()];
+        let mut frontier = BinaryHeap::new();
+
+        costs[self.index(from)] = 0.;
+        frontier.push(Frontier {
+            priority: self.heuristic(from, to),
+            index: self.index(from),
+        });
+
+        while let Some(Frontier { index, .. }) = frontier.pop() {
+            if closed[index] {
+                continue;
+            }
+            closed[index] = true;
+
+            let tile = self.tile_at(index);
+            if tile == to {
+                return Some(self.trace(&parents, index));
+            }
+
+            let parent = (parents[index] != usize::MAX).then(|| self.tile_at(parents[index]));
+            let successors = match jump_points {
+                true => self.jump_successors(tile, parent, to),
+                false => DIRECTIONS
+                    .into_iter()
+                    .filter(|&dir| self.can_step(tile, dir))
+                    .map(|dir| tile + dir)
+                    .collect(),
+            };
+
+            for next in successors {
+                let next_index = self.index(next);
+                let cost = costs[index] + self.distance(tile, next);
+                if cost < costs[next_index] {
+                    costs[next_index] = cost;
+                    parents[next_index] = index;
+                    frontier.push(Frontier {
+                        priority: cost + self.heuristic(next, to),
+                        index: next_index,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn can_step(&self, tile: IVec2, dir: IVec2) -> bool {
+        if !self.open_at(tile + dir) {
+            return false;
+        }
+
+        if dir.x == 0 || dir.y == 0 {
+            return true;
+        }
+
+        let horz = self.open_at(tile + IVec2::new(dir.x, 0));
+        let vert = self.open_at(tile + IVec2::new(0, dir.y));
+        match self.settings.connectivity {
+            Connectivity::Four => false,
+            Connectivity::Eight(CornerCutting::Never) => horz && vert,
+            Connectivity::Eight(CornerCutting::IfEitherOpen) => horz || vert,
+            Connectivity::Eight(CornerCutting::Always) => true,
+        }
+    }
+
+    // Jump point search, pruning neighbors as if corners cannot be cut
+    fn jump_successors(&self, tile: IVec2, parent: Option<IVec2>, to: IVec2) -> Vec<IVec2> {
+        let dirs = match parent.map(|parent| (tile - parent).signum()) {
+            None => DIRECTIONS.to_vec(),
+            Some(dir) if dir.x != 0 && dir.y != 0 => {
+                vec![dir, IVec2::new(dir.x, 0), IVec2::new(0, dir.y)]
+            }
+            Some(dir) if dir.x != 0 => vec![
+                dir,
+                IVec2::new(dir.x, 1),
+                IVec2::new(dir.x, -1),
+                IVec2::Y,
+                IVec2::NEG_Y,
+            ],
+            Some(dir) => vec![
+                dir,
+                IVec2::new(1, dir.y),
+                IVec2::new(-1, dir.y),
+                IVec2::X,
+                IVec2::NEG_X,
+            ],
+        };
+
+        dirs.into_iter()
+            .filter(|&dir| self.can_step(tile, dir))
+            .filter_map(|dir| self.jump(tile + dir, dir, to))
+            .collect()
+    }
+
+    fn jump(&self, mut tile: IVec2, dir: IVec2, to: IVec2) -> Option<IVec2> {
+        if !self.open_at(tile) {
+            return None;
+        }
+
+        loop {
+            if tile == to {
+                return Some(tile);
+            }
+
+            let forced = match (dir.x, dir.y) {
+                (0, _) => {
+                    (self.open_at(tile - IVec2::X) && !self.open_at(tile + IVec2::new(-1, -dir.y)))
+                        || (self.open_at(tile + IVec2::X)
+                            && !self.open_at(tile + IVec2::new(1, -dir.y)))
+                }
+                (_, 0) => {
+                    (self.open_at(tile - IVec2::Y) && !self.open_at(tile + IVec2::new(-dir.x, -1)))
+                        || (self.open_at(tile + IVec2::Y)
+                            && !self.open_at(tile + IVec2::new(-dir.x, 1)))
+                }
+                _ => {
+                    let horz = IVec2::new(dir.x, 0);
+                    let vert = IVec2::new(0, dir.y);
+                    self.jump(tile + horz, horz, to).is_some()
+                        || self.jump(tile + vert, vert, to).is_some()
+                }
+            };
+
+            if forced {
+                return Some(tile);
+            }
+
+            if !self.can_step(tile, dir) {
+                return None;
+            }
+
+            tile += dir;
+        }
+    }
+
+    fn trace(&self, parents: &[usize], mut index: usize) -> Vec<UVec2> {
+        let mut path = vec![self.tile_at(index)];
+        while parents[index] != usize::MAX {
+            let parent = self.tile_at(parents[index]);
+            let mut tile = *path.last().unwrap();
+            // Jump points may be several tiles apart
+            while tile != parent {
+                tile += (parent - tile).signum();
+                path.push(tile);
+            }
+
+            index = parents[index];
+        }
+
+        path.iter().rev().map(IVec2::as_uvec2).collect()
+    }
+
+    fn distance(&self, tile1: IVec2, tile2: IVec2) -> f32 {
+        ((tile2 - tile1).as_vec2() * self.tile_size).length()
+    }
+
+    fn heuristic(&self, tile: IVec2, to: IVec2) -> f32 {
+        let dist = (to - tile).abs().as_vec2() * self.tile_size;
+        match self.settings.connectivity {
+            Connectivity::Four => dist.x + dist.y,
+            Connectivity::Eight(_) => dist.length(),
+        }
+    }
+
+    fn open_at(&self, tile: IVec2) -> bool {
+        tile.cmpge(IVec2::ZERO).all() && self.is_open(tile.as_uvec2())
+    }
+
+    fn index(&self, tile: IVec2) -> usize {
+        (tile.y as u32 * self.map_size.x + tile.x as u32) as usize
+    }
+
+    fn tile_at(&self, index: usize) -> IVec2 {
+        UVec2::new(
+            index as u32 % self.map_size.x,
+            index as u32 / self.map_size.x,
+        )
+        .as_ivec2()
+    }
+}
+
+impl PathMesh for TileGrid {
+    fn find_path(&self, from: Vec2, to: Vec2, _: NavQuery, _: NavPathMode) -> Option<Vec<Vec2>> {
+        let tiles = self.find_tile_path(self.closest_tile(from)?, self.closest_tile(to)?)?;
+        Some(
+            [from]
+                .into_iter()
+                .chain(
+                    tiles
+                        .into_iter()
+                        .map(|tile| self.tile_center(tile))
+                        .skip_while(|&center| center == from),
+                )
+                .collect(),
+        )
+    }
+
+    fn closest_point(&self, point: Vec2, _: NavQuery) -> Option<Vec2> {
+        self.closest_tile(point).map(|tile| self.tile_center(tile))
+    }
+
+    fn node(&self, point: Vec2, _: NavQuery) -> Option<usize> {
+        self.closest_tile(point)
+            .map(|tile| self.index(tile.as_ivec2()))
+    }
+
+    fn distance_to_mesh(&self, point: Vec2, _: NavQuery) -> Option<f32> {
+        // Distance to the closest open tile, rather than to its center
+        let min = self.closest_tile(point)?.as_vec2() * self.tile_size;
+        Some(point.distance(point.clamp(min, min + self.tile_size)))
+    }
+
+    fn polygons(&self) -> Vec<Vec<Vec2>> {
+        (0..self.open.len())
+            .filter(|&index| self.open[index])
+            .map(|index| {
+                let min = self.tile_at(index).as_vec2() * self.tile_size;
+                let max = min + self.tile_size;
+                vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+            })
+            .collect()
+    }
+
+    fn line_of_sight(&self, from: Vec2, to: Vec2, _: NavQuery) -> bool {
+        // Check points along the line, close enough together not to skip a tile
+        let steps = ((to - from).abs() / self.tile_size * 2.)
+            .max_element()
+            .ceil() as usize;
+        (0..=steps).all(|step| {
+            let point = from.lerp(to, step as f32 / steps.max(1) as f32);
+            self.tile(point).is_some_and(|tile| self.is_open(tile))
+        })
+    }
+
+    fn graph(&self) -> NavGraph {
+        NavGraph::new((0..self.open.len()).map(|index| {
+            let tile = self.tile_at(index);
+            let edges = match self.open[index] {
+                true => DIRECTIONS
+                    .into_iter()
+                    .filter(|&dir| self.can_step(tile, dir))
+                    .map(|dir| NavEdge {
+                        to: self.index(tile + dir),
+                        portal: self.tile_center((tile + dir).as_uvec2()),
+                        cost: self.distance(tile, tile + dir),
+                    })
+                    .collect(),
+                false => Vec::new(),
+            };
+
+            (self.tile_center(tile.as_uvec2()), edges)
+        }))
+    }
+}
+
+impl Navmeshes<TileGrid> {
+    /// Generate tile grids for your tilemap. The input to `navability` is a tile's position.
+    /// `clearances` will be sorted for you.
+    pub fn generate_grid(
+        map_size: UVec2,
+        tile_size: Vec2,
+        navability: impl Fn(UVec2) -> Navability,
+        clearances: impl IntoIterator<Item = f32>,
+        settings: GridSettings,
+    ) -> Self {
+        Self::new(clearances.into_iter().map(|clearance| {
+            (
+                clearance,
+                TileGrid::generate(map_size, tile_size, &navability, clearance, settings),
+            )
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[&[&str]] = &[
+        &[
+            "..........",
+            ".####.....",
+            "....#..#..",
+            ".#..#..#..",
+            ".#.....#..",
+            ".#######..",
+            "..........",
+        ],
+        &[
+            "....#.....",
+            "..#.#.###.",
+            "..#...#...",
+            "..#####.#.",
+            "........#.",
+            ".##.#####.",
+            "....#.....",
+        ],
+        &[
+            ".#.#.#.#.",
+            "....#....",
+            ".#.#.#.#.",
+            "#...#...#",
+            ".#.#.#.#.",
+        ],
+    ];
+
+    // Row 0 of the fixture is tile row 0. `#` is solid.
+    fn grid(rows: &[&str], settings: GridSettings) -> TileGrid {
+        let map_size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+        TileGrid::generate(
+            map_size,
+            Vec2::ONE,
+            |tile| match rows[tile.y as usize].as_bytes()[tile.x as usize] {
+                b'#' => Navability::Solid,
+                _ => Navability::Navable,
+            },
+            0.,
+            settings,
+        )
+    }
+
+    fn open_tiles(grid: &TileGrid) -> Vec<UVec2> {
+        (0..grid.map_size().y)
+            .flat_map(|y| (0..grid.map_size().x).map(move |x| UVec2::new(x, y)))
+            .filter(|&tile| grid.is_open(tile))
+            .collect()
+    }
+
+    fn cost(path: &[UVec2]) -> f32 {
+        path.windows(2)
+            .map(|tiles| tiles[0].as_vec2().distance(tiles[1].as_vec2()))
+            .sum()
+    }
+
+    #[test]
+    fn paths_are_optimal() {
+        for connectivity in [
+            Connectivity::Four,
+            Connectivity::Eight(CornerCutting::Never),
+            Connectivity::Eight(CornerCutting::IfEitherOpen),
+            Connectivity::Eight(CornerCutting::Always),
+        ] {
+            for rows in FIXTURES {
+                let grid = grid(
+                    rows,
+                    GridSettings {
+                        connectivity,
+                        jump_points: false,
+                    },
+                );
+                let graph = grid.graph();
+
+                for &from in &open_tiles(&grid) {
+                    let (costs, _) = graph.dijkstra([(grid.index(from.as_ivec2()), 0.)]);
+                    for &to in &open_tiles(&grid) {
+                        let best = costs[grid.index(to.as_ivec2())];
+                        match grid.find_tile_path(from, to) {
+                            Some(path) => {
+                                assert_eq!(path.first(), Some(&from));
+                                assert_eq!(path.last(), Some(&to));
+                                for tiles in path.windows(2) {
+                                    let dir = tiles[1].as_ivec2() - tiles[0].as_ivec2();
+                                    assert!(grid.can_step(tiles[0].as_ivec2(), dir));
+                                }
+                                assert!((cost(&path) - best).abs() < 1e-4);
+                            }
+                            None => assert_eq!(best, f32::INFINITY),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jump_points_match_a_star() {
+        for rows in FIXTURES {
+            let a_star = grid(rows, GridSettings::default());
+            let jps = grid(
+                rows,
+                GridSettings {
+                    jump_points: true,
+                    ..GridSettings::default()
+                },
+            );
+
+            for &from in &open_tiles(&a_star) {
+                for &to in &open_tiles(&a_star) {
+                    let expected = a_star.find_tile_path(from, to);
+                    let actual = jps.find_tile_path(from, to);
+                    assert_eq!(expected.is_some(), actual.is_some());
+                    if let (Some(expected), Some(actual)) = (expected, actual) {
+                        // Paths may differ between equally short routes
+                        assert!((cost(&expected) - cost(&actual)).abs() < 1e-4);
+                        for tiles in actual.windows(2) {
+                            let dir = tiles[1].as_ivec2() - tiles[0].as_ivec2();
+                            assert!(jps.can_step(tiles[0].as_ivec2(), dir));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closed_tiles_have_no_paths() {
+        let grid = grid(FIXTURES[0], GridSettings::default());
+        assert_eq!(grid.find_tile_path(UVec2::new(1, 1), UVec2::ZERO), None);
+        let closest = grid.closest_tile(Vec2::new(1.5, 1.5)).unwrap();
+        assert!(grid.is_open(closest));
+        assert_eq!(grid.tile_center(closest).distance(Vec2::new(1.5, 1.5)), 1.);
+        assert!(grid.graph().edges(grid.index(IVec2::new(1, 1))).is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+//! Hierarchical pathfinding for long-distance queries
+
+use std::collections::BinaryHeap;
+
+use glam::{IVec2, UVec2, Vec2};
+use navmesh::{NavPathMode, NavQuery};
+
+use crate::{graph::NavGraph, mesh::PathMesh, search::Frontier};
+
+/// Coarse graph over a [`PathMesh`]. The map is split into clusters of tiles, and each
+/// cluster's node, the navigable point closest to its center, is connected to the nodes
+/// of neighboring clusters. Long paths are planned through the nodes first, and then refined
+/// with the [`PathMesh`] one leg at a time.
+#[derive(Clone, Debug)]
+pub struct NavHierarchy {
+    cluster_size: Vec2,
+    clusters: UVec2,
+    nodes: Vec<Option<Vec2>>,
+    // Node of the mesh's graph that each cluster's node is in
+    graph_nodes: Vec<Option<usize>>,
+    edges: Vec<Vec<(usize, f32)>>,
+}
+
+impl NavHierarchy {
+    /// Precompute a hierarchy for the given mesh. `map_size` and `tile_size` should match
+    /// the tilemap the mesh was generated from. `cluster_size` is in tiles.
+    pub fn generate(
+        mesh: &impl PathMesh,
+        map_size: UVec2,
+        tile_size: Vec2,
+        cluster_size: UVec2,
+    ) -> Self {
+        let cluster_size = cluster_size.max(UVec2::ONE);
+        let clusters = (map_size + cluster_size - 1) / cluster_size;
+        let cluster_size = cluster_size.as_vec2() * tile_size;
+
+        let nodes = (0..clusters.y)
+            .flat_map(|y| (0..clusters.x).map(move |x| UVec2::new(x, y)))
+            .map(|cluster| {
+                let min = cluster.as_vec2() * cluster_size;
+                mesh.closest_point(min + cluster_size / 2., NavQuery::Accuracy)
+                    .filter(|node| node.cmpge(min).all() && node.cmplt(min + cluster_size).all())
+            })
+            .collect::<Vec<_>>();
+        let graph_nodes = nodes
+            .iter()
+            .map(|node| mesh.node((*node)?, NavQuery::Accuracy))
+            .collect();
+
+        let mut hierarchy = Self {
+            cluster_size,
+            clusters,
+            edges: vec![Vec::new(); nodes.len()],
+            nodes,
+            graph_nodes,
+        };
+
+        for index in 0..hierarchy.nodes.len() {
+            let Some(node) = hierarchy.nodes[index] else {
+                continue;
+            };
+
+            // Each pair is only pathed once, from the earlier cluster
+            let neighborhood = hierarchy
+                .neighborhood(hierarchy.cluster_at(index))
+                .collect::<Vec<_>>();
+            for (next_index, next) in neighborhood {
+                if next_index <= index {
+                    continue;
+                }
+
+                if let Some(cost) = path_cost(mesh, node, next) {
+                    hierarchy.edges[index].push((next_index, cost));
+                    hierarchy.edges[next_index].push((index, cost));
+                }
+            }
+        }
+
+        hierarchy
+    }
+
+    /// Gets the size of each cluster
+    pub fn cluster_size(&self) -> Vec2 {
+        self.cluster_size
+    }
+
+    /// Whether `from` and `to` are far enough apart that the hierarchy should be used.
+    /// Points in the same or neighboring clusters are not.
+    pub fn is_long(&self, from: Vec2, to: Vec2) -> bool {
+        (self.cluster(from) - self.cluster(to)).abs().max_element() > 1
+    }
+
+    /// Find a coarse path from `from` to `to` through the clusters' nodes. The returned
+    /// waypoints exclude `from` and end at `to`. Each leg between waypoints can be refined
+    /// with the [`PathMesh`] this hierarchy was generated for. `graph` should be that mesh's
+    /// [`PathMesh::graph`], such as from [`Navmeshes::graph`](crate::mesh::Navmeshes::graph).
+    pub fn find_path(
+        &self,
+        mesh: &impl PathMesh,
+        graph: &NavGraph,
+        from: Vec2,
+        to: Vec2,
+        query: NavQuery,
+    ) -> Option<Vec<Vec2>> {
+        let start = self.nodes.len();
+        let goal = start + 1;
+        // Neighboring nodes are at most two clusters away, so one search of the graph
+        // around the point reaches all of them, unless they're behind a long detour
+        let budget = self.cluster_size.length() * 4.;
+        let links = |point: Vec2| {
+            let Some(source) = mesh.node(point, query) else {
+                return Vec::new();
+            };
+            let (costs, _) =
+                graph.dijkstra_within([(source, point.distance(graph.position(source)))], budget);
+
+            self.neighborhood(self.cluster(point))
+                .filter_map(|(index, node)| {
+                    let graph_node = self.graph_nodes[index]?;
+                    let cost = costs[graph_node] + graph.position(graph_node).distance(node);
+                    cost.is_finite().then_some((index, cost))
+                })
+                .collect::<Vec<_>>()
+        };
+        let start_links = links(from);
+        let goal_links = links(to);
+
+        let mut costs = vec![f32::INFINITY; goal + 1];
+        let mut parents = vec![usize::MAX; goal + 1];
+        let mut frontier = BinaryHeap::new();
+
+        costs[start] = 0.;
+        frontier.push(Frontier {
+            priority: from.distance(to),
+            index: start,
+        });
+
+        while let Some(Frontier { priority, index }) = frontier.pop() {
+            if index == goal {
+                let mut path = vec![to];
+                let mut index = parents[index];
+                while index != start {
+                    path.push(self.nodes[index].unwrap());
+                    index = parents[index];
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            let pos = match index == start {
+                true => from,
+                false => self.nodes[index].unwrap(),
+            };
+            if priority > costs[index] + pos.distance(to) {
+                continue;
+            }
+
+            let successors = match index == start {
+                true => start_links.clone(),
+                false => self.edges[index]
+                    .iter()
+                    .copied()
+                    .chain(
+                        goal_links
+                            .iter()
+                            .filter(|&&(link, _)| link == index)
+                            .map(|&(_, cost)| (goal, cost)),
+                    )
+                    .collect(),
+            };
+
+            for (next, cost) in successors {
+                let cost = costs[index] + cost;
+                if cost < costs[next] {
+                    costs[next] = cost;
+                    parents[next] = index;
+                    frontier.push(Frontier {
+                        priority: cost
+                            + match next == goal {
+                                true => 0.,
+                                false => self.nodes[next].unwrap().distance(to),
+                            },
+                        index: next,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn cluster(&self, point: Vec2) -> IVec2 {
+        // Not `clamp`, which panics for an empty map
+        (point / self.cluster_size)
+            .floor()
+            .as_ivec2()
+            .min(self.clusters.as_ivec2() - 1)
+            .max(IVec2::ZERO)
+    }
+
+    fn cluster_at(&self, index: usize) -> IVec2 {
+        IVec2::new(
+            (index as u32 % self.clusters.x) as i32,
+            (index as u32 / self.clusters.x) as i32,
+        )
+    }
+
+    // Nodes of the given cluster and the clusters around it
+    fn neighborhood(&self, cluster: IVec2) -> impl Iterator<Item = (usize, Vec2)> + '_ {
+        (-1..=1)
+            .flat_map(move |y| (-1..=1).map(move |x| cluster + IVec2::new(x, y)))
+            .filter(|cluster| {
+                cluster.cmpge(IVec2::ZERO).all() && cluster.cmplt(self.clusters.as_ivec2()).all()
+            })
+            .filter_map(|cluster| {
+                let index = (cluster.y as u32 * self.clusters.x + cluster.x as u32) as usize;
+                self.nodes[index].map(|node| (index, node))
+            })
+    }
+}
+
+fn path_cost(mesh: &impl PathMesh, from: Vec2, to: Vec2) -> Option<f32> {
+    mesh.find_path(from, to, NavQuery::Accuracy, NavPathMode::Accuracy)
+        .map(|path| length(&path))
+}
+
+fn length(path: &[Vec2]) -> f32 {
+    path.windows(2)
+        .map(|segment| segment[0].distance(segment[1]))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        grid::{GridSettings, TileGrid},
+        mesh::Navability,
+    };
+
+    // 32x32 map split by a wall at x = 15, with a gap at the top
+    fn grid(gap: bool) -> TileGrid {
+        TileGrid::generate(
+            UVec2::splat(32),
+            Vec2::ONE,
+            |tile| match tile.x == 15 && (tile.y < 28 || !gap) {
+                true => Navability::Solid,
+                false => Navability::Navable,
+            },
+            0.,
+            GridSettings::default(),
+        )
+    }
+
+    #[test]
+    fn coarse_paths_refine_to_the_target() {
+        let grid = grid(true);
+        let hierarchy = NavHierarchy::generate(&grid, UVec2::splat(32), Vec2::ONE, UVec2::splat(4));
+        let (from, to) = (Vec2::new(2.5, 2.5), Vec2::new(29.5, 2.5));
+        assert!(hierarchy.is_long(from, to));
+        assert!(!hierarchy.is_long(from, Vec2::new(6.5, 6.5)));
+
+        let coarse = hierarchy
+            .find_path(&grid, &grid.graph(), from, to, NavQuery::Accuracy)
+            .unwrap();
+        assert_eq!(coarse.last(), Some(&to));
+
+        let mut leg_start = from;
+        let mut total = 0.;
+        for waypoint in coarse {
+            let leg = grid
+                .find_path(
+                    leg_start,
+                    waypoint,
+                    NavQuery::Accuracy,
+                    NavPathMode::Accuracy,
+                )
+                .unwrap();
+            total += length(&leg);
+            leg_start = waypoint;
+        }
+
+        let direct = path_cost(&grid, from, to).unwrap();
+        assert!(total >= direct - 1e-4);
+        assert!(total <= direct * 1.25);
+    }
+
+    #[test]
+    fn disconnected_regions_have_no_coarse_path() {
+        let grid = grid(false);
+        let hierarchy = NavHierarchy::generate(&grid, UVec2::splat(32), Vec2::ONE, UVec2::splat(4));
+        let path = hierarchy.find_path(
+            &grid,
+            &grid.graph(),
+            Vec2::new(2.5, 2.5),
+            Vec2::new(29.5, 2.5),
+            NavQuery::Accuracy,
+        );
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn empty_maps_have_no_clusters() {
+        let grid = TileGrid::generate(
+            UVec2::ZERO,
+            Vec2::ONE,
+            |_| Navability::Navable,
+            0.,
+            GridSettings::default(),
+        );
+        let hierarchy = NavHierarchy::generate(&grid, UVec2::ZERO, Vec2::ONE, UVec2::splat(4));
+        assert!(!hierarchy.is_long(Vec2::ZERO, Vec2::splat(100.)));
+        let path = hierarchy.find_path(
+            &grid,
+            &grid.graph(),
+            Vec2::ZERO,
+            Vec2::splat(100.),
+            NavQuery::Accuracy,
+        );
+        assert_eq!(path, None);
+    }
+
+    // Counts the exact searches made of the grid it wraps
+    struct Counting {
+        grid: TileGrid,
+        searches: AtomicUsize,
+    }
+
+    impl PathMesh for Counting {
+        fn find_path(
+            &self,
+            from: Vec2,
+            to: Vec2,
+            query: NavQuery,
+            mode: NavPathMode,
+        ) -> Option<Vec<Vec2>> {
+            self.searches.fetch_add(1, Ordering::Relaxed);
+            self.grid.find_path(from, to, query, mode)
+        }
+
+        fn closest_point(&self, point: Vec2, query: NavQuery) -> Option<Vec2> {
+            self.grid.closest_point(point, query)
+        }
+
+        fn node(&self, point: Vec2, query: NavQuery) -> Option<usize> {
+            self.grid.node(point, query)
+        }
+
+        fn graph(&self) -> NavGraph {
+            self.grid.graph()
+        }
+    }
+
+    #[test]
+    fn coarse_paths_do_not_search_the_mesh() {
+        let size = UVec2::splat(128);
+        let mesh = Counting {
+            grid: TileGrid::generate(
+                size,
+                Vec2::ONE,
+                // Walls with gaps at alternating ends
+                |tile| match tile.x % 16 == 8 && (tile.y < 120) == (tile.x % 32 == 8) {
+                    true => Navability::Solid,
+                    false => Navability::Navable,
+                },
+                0.,
+                GridSettings::default(),
+            ),
+            searches: AtomicUsize::new(0),
+        };
+        let hierarchy = NavHierarchy::generate(&mesh, size, Vec2::ONE, UVec2::splat(8));
+        let graph = mesh.graph();
+        mesh.searches.store(0, Ordering::Relaxed);
+
+        let (from, to) = (Vec2::new(2.5, 2.5), Vec2::new(125.5, 2.5));
+        let coarse = hierarchy
+            .find_path(&mesh, &graph, from, to, NavQuery::Accuracy)
+            .unwrap();
+        assert_eq!(mesh.searches.load(Ordering::Relaxed), 0);
+        assert_eq!(coarse.last(), Some(&to));
+        // It goes through the gaps
+        assert!(coarse.iter().any(|waypoint| waypoint.y > 112.));
+    }
+}
@@ -0,0 +1,210 @@
+//! Saving navmeshes as JSON, and loading them again without generating them
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+use glam::{UVec2, Vec2};
+use navmesh::{NavMesh, NavTriangle, NavVec3};
+use serde::{Deserialize, Serialize};
+
+use crate::mesh::{NavmeshGenError, Navmeshes};
+
+/// Navmeshes of a tilemap, in a form that can be serialized. Only the meshes are kept, so
+/// a hierarchy has to be built again with [`Navmeshes::with_hierarchy`] after loading.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NavmeshesData {
+    /// Size of the tilemap in tiles
+    pub map_size: [u32; 2],
+    /// Size of each tile
+    pub tile_size: [f32; 2],
+    /// Navmeshes, in order of clearance
+    pub navmeshes: Vec<NavmeshData>,
+}
+
+/// Navmesh for one clearance, in a form that can be serialized
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NavmeshData {
+    /// Clearance that the navmesh was generated with
+    pub clearance: f32,
+    /// Vertices of the navmesh
+    pub vertices: Vec<[f32; 2]>,
+    /// Triangles of the navmesh, as indices into `vertices`
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl NavmeshesData {
+    /// Create a `NavmeshesData` from a tilemap's navmeshes
+    pub fn new(map_size: UVec2, tile_size: Vec2, navmeshes: &Navmeshes) -> Self {
+        Self {
+            map_size: map_size.to_array(),
+            tile_size: tile_size.to_array(),
+            navmeshes: (0..navmeshes.mesh_count())
+                .filter_map(|mesh| Some((navmeshes.clearance_at(mesh)?, navmeshes.mesh_at(mesh)?)))
+                .map(|(clearance, mesh)| NavmeshData {
+                    clearance,
+                    vertices: mesh
+                        .vertices()
+                        .iter()
+                        .map(|vertex| [vertex.x, vertex.y])
+                        .collect(),
+                    triangles: mesh
+                        .triangles()
+                        .iter()
+                        .map(|triangle| [triangle.first, triangle.second, triangle.third])
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Build the navmeshes again
+    pub fn navmeshes(&self) -> Result<Navmeshes, NavmeshGenError> {
+        let navmeshes = self
+            .navmeshes
+            .iter()
+            .map(|navmesh| {
+                let vertices = navmesh
+                    .vertices
+                    .iter()
+                    .map(|&[x, y]| NavVec3::new(x, y, 0.))
+                    .collect();
+                let triangles = navmesh
+                    .triangles
+                    .iter()
+                    .map(|&[first, second, third]| NavTriangle {
+                        first,
+                        second,
+                        third,
+                    })
+                    .collect();
+                Ok((navmesh.clearance, NavMesh::new(vertices, triangles)?))
+            })
+            .collect::<Result<Vec<_>, NavmeshGenError>>()?;
+
+        Ok(Navmeshes::new(navmeshes))
+    }
+
+    /// Serialize as JSON
+    pub fn to_json(&self) -> String {
+        // Numbers and arrays of them always serialize
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Deserialize from JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Load navmeshes saved as JSON, such as by the `seldom_map_nav` command-line tool's `export`
+/// command, along with the size of their tilemap and its tiles
+pub fn load_navmeshes(path: impl AsRef<Path>) -> Result<(UVec2, Vec2, Navmeshes), JsonError> {
+    let data = NavmeshesData::from_json(&fs::read_to_string(path)?)?;
+    Ok((
+        UVec2::from(data.map_size),
+        Vec2::from(data.tile_size),
+        data.navmeshes()?,
+    ))
+}
+
+/// Error that can emit when loading navmeshes from JSON
+#[derive(Debug)]
+pub enum JsonError {
+    /// Error reading the file
+    Io(io::Error),
+    /// Error parsing the JSON
+    Json(serde_json::Error),
+    /// The navmeshes are malformed
+    Navmesh(NavmeshGenError),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read navmeshes: {error}"),
+            Self::Json(error) => write!(f, "could not parse navmeshes: {error}"),
+            Self::Navmesh(error) => write!(f, "invalid navmeshes: {error}"),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl From<io::Error> for JsonError {
+    fn from(error: io::Error) -> Self {
+        JsonError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(error: serde_json::Error) -> Self {
+        JsonError::Json(error)
+    }
+}
+
+impl From<NavmeshGenError> for JsonError {
+    fn from(error: NavmeshGenError) -> Self {
+        JsonError::Navmesh(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::Navability;
+
+    use super::*;
+
+    #[test]
+    fn navmeshes_round_trip() {
+        let map_size = UVec2::new(6, 4);
+        let tile_size = Vec2::new(2., 1.);
+        let navmeshes = Navmeshes::generate(
+            map_size,
+            tile_size,
+            |tile| match tile == UVec2::new(2, 1) {
+                true => Navability::Solid,
+                false => Navability::Navable,
+            },
+            [0., 0.25],
+        )
+        .unwrap();
+
+        let data = NavmeshesData::new(map_size, tile_size, &navmeshes);
+        let loaded = NavmeshesData::from_json(&data.to_json()).unwrap();
+        assert_eq!(loaded, data);
+        assert_eq!(loaded.map_size, [6, 4]);
+        assert_eq!(loaded.tile_size, [2., 1.]);
+
+        let loaded = loaded.navmeshes().unwrap();
+        assert_eq!(loaded.mesh_count(), 2);
+        for mesh in 0..2 {
+            assert_eq!(loaded.clearance_at(mesh), navmeshes.clearance_at(mesh));
+            let (original, loaded) = (
+                navmeshes.mesh_at(mesh).unwrap(),
+                loaded.mesh_at(mesh).unwrap(),
+            );
+            assert_eq!(original.vertices(), loaded.vertices());
+            assert_eq!(original.triangles().len(), loaded.triangles().len());
+        }
+        assert_eq!(NavmeshesData::new(map_size, tile_size, &loaded), data);
+    }
+
+    #[test]
+    fn out_of_bounds_triangles_are_rejected() {
+        let data = NavmeshesData::from_json(
+            r#"{
+                "map_size": [1, 1],
+                "tile_size": [1, 1],
+                "navmeshes": [
+                    { "clearance": 0, "vertices": [[0, 0], [1, 0]], "triangles": [[0, 1, 2]] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(data.navmeshes(), Err(NavmeshGenError::Navmesh(_))));
+    }
+}
@@ -0,0 +1,500 @@
+//! Loading navability from [LDtk](https://ldtk.io/) projects
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+use glam::{UVec2, Vec2};
+use navmesh::{NavMesh, NavVec3};
+use serde::Deserialize;
+
+use crate::mesh::{generate_navmesh, Navability, NavmeshGenError, Navmeshes};
+
+/// Which IntGrid layer holds navability, and the [`Navability`] of each IntGrid value
+#[derive(Clone, Debug)]
+pub struct IntGridNavability {
+    /// Identifier of the IntGrid layer
+    pub layer: String,
+    /// Navability of each IntGrid value
+    pub values: HashMap<i64, Navability>,
+    /// Navability of empty cells, and of values that aren't in `values`
+    pub default: Navability,
+}
+
+impl IntGridNavability {
+    /// Create an `IntGridNavability`. Empty cells and other values are navigable.
+    pub fn new(
+        layer: impl Into<String>,
+        values: impl IntoIterator<Item = (i64, Navability)>,
+    ) -> Self {
+        Self {
+            layer: layer.into(),
+            values: values.into_iter().collect(),
+            default: Navability::Navable,
+        }
+    }
+}
+
+/// Navability of an LDtk level's cells. Cell row 0 is the top row, like in LDtk.
+/// LDtk's y axis points down, so by default, the navmeshes are upside down in a world where
+/// y points up, like Bevy's. Set `y_up` to place them upright.
+#[derive(Clone, Debug)]
+pub struct LdtkLevel {
+    /// Identifier of the level
+    pub identifier: String,
+    /// Size of the level's IntGrid layer in cells
+    pub map_size: UVec2,
+    /// Size of each cell, in pixels
+    pub tile_size: Vec2,
+    /// Position of the top left corner of the level's IntGrid layer in LDtk's world, in pixels,
+    /// with y pointing down
+    pub offset: Vec2,
+    /// Navability of each cell, row by row
+    pub tiles: Vec<Navability>,
+    /// Whether y points up on the navmesh, by negating LDtk's world y coordinates, so the levels
+    /// are upright and still line up. Otherwise, the navmesh is in LDtk's world coordinates.
+    /// [`LdtkLevel::to_pixel`] and [`LdtkLevel::from_pixel`] account for it.
+    pub y_up: bool,
+}
+
+impl LdtkLevel {
+    /// Navability of the cell at the given position
+    pub fn navability(&self, position: UVec2) -> Navability {
+        self.tiles[(position.y * self.map_size.x + position.x) as usize]
+    }
+
+    /// Generate navmeshes for the level, with each of the given clearances. The navmeshes are
+    /// in world coordinates, so the levels of a world line up.
+    pub fn navmeshes(
+        &self,
+        clearances: impl IntoIterator<Item = f32>,
+    ) -> Result<Navmeshes, NavmeshGenError> {
+        let min = self.min();
+        let offset = NavVec3::new(min.x, min.y, 0.);
+        let navmeshes = clearances
+            .into_iter()
+            .map(|clearance| {
+                let navmesh = generate_navmesh(
+                    self.map_size,
+                    self.tile_size,
+                    |position| match self.y_up {
+                        true => self
+                            .navability(UVec2::new(position.x, self.map_size.y - 1 - position.y)),
+                        false => self.navability(position),
+                    },
+                    clearance,
+                )?;
+                let vertices = navmesh
+                    .vertices()
+                    .iter()
+                    .map(|&vertex| vertex + offset)
+                    .collect();
+                Ok((
+                    clearance,
+                    NavMesh::new(vertices, navmesh.triangles().to_vec())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, NavmeshGenError>>()?;
+
+        Ok(Navmeshes::new(navmeshes))
+    }
+
+    /// Whether the level contains the given point on the navmesh
+    pub fn contains(&self, point: Vec2) -> bool {
+        let min = self.min();
+        let max = min + self.map_size.as_vec2() * self.tile_size;
+        point.cmpge(min).all() && point.cmplt(max).all()
+    }
+
+    /// Convert a point on the navmesh to LDtk's world pixel coordinates, where y points down
+    pub fn to_pixel(&self, point: Vec2) -> Vec2 {
+        match self.y_up {
+            true => Vec2::new(point.x, -point.y),
+            false => point,
+        }
+    }
+
+    /// Convert a point in LDtk's world pixel coordinates to a point on the navmesh.
+    /// The inverse of [`LdtkLevel::to_pixel`].
+    pub fn from_pixel(&self, pixel: Vec2) -> Vec2 {
+        self.to_pixel(pixel)
+    }
+
+    // Corner of the level's IntGrid layer with the lowest coordinates on the navmesh
+    fn min(&self) -> Vec2 {
+        match self.y_up {
+            true => Vec2::new(
+                self.offset.x,
+                -self.offset.y - self.map_size.y as f32 * self.tile_size.y,
+            ),
+            false => self.offset,
+        }
+    }
+}
+
+/// Load the levels of an `.ldtk` project. Levels saved in separate `.ldtkl` files are loaded
+/// relative to the project. Levels without the IntGrid layer are skipped.
+pub fn load_levels(
+    path: impl AsRef<Path>,
+    navability: &IntGridNavability,
+) -> Result<Vec<LdtkLevel>, LdtkError> {
+    let path = path.as_ref();
+    parse_levels(
+        &fs::read_to_string(path)?,
+        path.parent().unwrap_or(Path::new("")),
+        navability,
+    )
+}
+
+/// Parse the levels of the contents of an `.ldtk` project. Levels saved in separate `.ldtkl`
+/// files are loaded relative to `directory`. Levels without the IntGrid layer are skipped.
+pub fn parse_levels(
+    ldtk: &str,
+    directory: &Path,
+    navability: &IntGridNavability,
+) -> Result<Vec<LdtkLevel>, LdtkError> {
+    let project = serde_json::from_str::<Project>(ldtk)?;
+    let worlds = match project.worlds.is_empty() {
+        true => vec![World {
+            world_layout: project.world_layout,
+            levels: project.levels,
+        }],
+        false => project.worlds,
+    };
+
+    let mut levels = Vec::new();
+    for world in worlds {
+        // Levels in linear layouts don't store their positions, so lay them out end to end
+        let mut next = Vec2::ZERO;
+        for level in world.levels {
+            let level = match (&level.layer_instances, &level.external_rel_path) {
+                (None, Some(external)) => {
+                    serde_json::from_str::<Level>(&fs::read_to_string(directory.join(external))?)?
+                }
+                _ => level,
+            };
+
+            let position = match world.world_layout {
+                Some(WorldLayout::LinearHorizontal) => {
+                    let position = next;
+                    next.x += level.px_wid as f32;
+                    position
+                }
+                Some(WorldLayout::LinearVertical) => {
+                    let position = next;
+                    next.y += level.px_hei as f32;
+                    position
+                }
+                _ => Vec2::new(level.world_x as f32, level.world_y as f32),
+            };
+
+            let Some(layer) = level
+                .layer_instances
+                .iter()
+                .flatten()
+                .find(|layer| layer.identifier == navability.layer)
+            else {
+                continue;
+            };
+
+            if layer.layer_type != "IntGrid" {
+                return Err(LdtkError::Invalid(format!(
+                    "layer `{}` is not an IntGrid layer",
+                    layer.identifier,
+                )));
+            }
+
+            let map_size = UVec2::new(layer.c_wid, layer.c_hei);
+            if layer.int_grid_csv.len() != (map_size.x * map_size.y) as usize {
+                return Err(LdtkError::Invalid(format!(
+                    "layer `{}` of level `{}` has {} cells, but should have {}",
+                    layer.identifier,
+                    level.identifier,
+                    layer.int_grid_csv.len(),
+                    map_size.x * map_size.y,
+                )));
+            }
+
+            levels.push(LdtkLevel {
+                identifier: level.identifier,
+                map_size,
+                tile_size: Vec2::splat(layer.grid_size as f32),
+                offset: position
+                    + Vec2::new(
+                        layer.px_total_offset_x as f32,
+                        layer.px_total_offset_y as f32,
+                    ),
+                tiles: layer
+                    .int_grid_csv
+                    .iter()
+                    .map(|value| {
+                        navability
+                            .values
+                            .get(value)
+                            .copied()
+                            .unwrap_or(navability.default)
+                    })
+                    .collect(),
+                y_up: false,
+            });
+        }
+    }
+
+    Ok(levels)
+}
+
+/// Error that can emit when loading an LDtk project
+#[derive(Debug)]
+pub enum LdtkError {
+    /// Error reading the project or a level
+    Io(io::Error),
+    /// Error parsing the JSON of the project or a level
+    Json(serde_json::Error),
+    /// The project or a level is malformed
+    Invalid(String),
+}
+
+impl Display for LdtkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read LDtk file: {error}"),
+            Self::Json(error) => write!(f, "could not parse LDtk file: {error}"),
+            Self::Invalid(reason) => write!(f, "invalid LDtk project: {reason}"),
+        }
+    }
+}
+
+impl Error for LdtkError {}
+
+impl From<io::Error> for LdtkError {
+    fn from(error: io::Error) -> Self {
+        LdtkError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LdtkError {
+    fn from(error: serde_json::Error) -> Self {
+        LdtkError::Json(error)
+    }
+}
+
+// The parts of the LDtk JSON format that are needed for navability
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    world_layout: Option<WorldLayout>,
+    #[serde(default)]
+    levels: Vec<Level>,
+    #[serde(default)]
+    worlds: Vec<World>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct World {
+    world_layout: Option<WorldLayout>,
+    levels: Vec<Level>,
+}
+
+#[derive(Deserialize)]
+enum WorldLayout {
+    Free,
+    GridVania,
+    LinearHorizontal,
+    LinearVertical,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Level {
+    identifier: String,
+    world_x: i64,
+    world_y: i64,
+    px_wid: i64,
+    px_hei: i64,
+    layer_instances: Option<Vec<LayerInstance>>,
+    external_rel_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__type")]
+    layer_type: String,
+    #[serde(rename = "__cWid")]
+    c_wid: u32,
+    #[serde(rename = "__cHei")]
+    c_hei: u32,
+    #[serde(rename = "__gridSize")]
+    grid_size: u32,
+    #[serde(rename = "__pxTotalOffsetX")]
+    px_total_offset_x: i64,
+    #[serde(rename = "__pxTotalOffsetY")]
+    px_total_offset_y: i64,
+    #[serde(default)]
+    int_grid_csv: Vec<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use navmesh::NavQuery;
+
+    use crate::mesh::PathMesh;
+
+    use super::*;
+
+    fn navability() -> IntGridNavability {
+        IntGridNavability::new(
+            "Collision",
+            [
+                (1, Navability::Solid),
+                (2, Navability::Solid),
+                (3, Navability::Navable),
+            ],
+        )
+    }
+
+    // Rows of the level, where `#` is a solid cell
+    fn rows(level: &LdtkLevel) -> Vec<String> {
+        (0..level.map_size.y)
+            .map(|y| {
+                (0..level.map_size.x)
+                    .map(|x| match level.navability(UVec2::new(x, y)) {
+                        Navability::Navable => '.',
+                        Navability::Solid => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn world() -> Vec<LdtkLevel> {
+        load_levels(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ldtk/world.ldtk"),
+            &navability(),
+        )
+        .unwrap()
+    }
+
+    // Project with one world of the given layout, and levels with the given positions and
+    // 2x1 IntGrid layers
+    fn project(layout: &str, layer_type: &str, cells: &str, levels: &[(i64, i64)]) -> String {
+        let levels = levels
+            .iter()
+            .enumerate()
+            .map(|(index, (x, y))| {
+                format!(
+                    r#"{{
+                        "identifier": "Level_{index}", "worldX": {x}, "worldY": {y},
+                        "pxWid": 16, "pxHei": 8,
+                        "layerInstances": [{{
+                            "__identifier": "Collision", "__type": "{layer_type}",
+                            "__cWid": 2, "__cHei": 1, "__gridSize": 8,
+                            "__pxTotalOffsetX": 0, "__pxTotalOffsetY": 4,
+                            "intGridCsv": [{cells}]
+                        }}]
+                    }}"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(r#"{{ "worldLayout": "{layout}", "levels": [{levels}] }}"#)
+    }
+
+    #[test]
+    fn world_levels_are_loaded_from_level_files() {
+        let levels = world();
+        assert_eq!(
+            levels
+                .iter()
+                .map(|level| (level.identifier.as_str(), level.offset, level.map_size))
+                .collect::<Vec<_>>(),
+            [
+                ("Level_0", Vec2::ZERO, UVec2::splat(16)),
+                ("Level_1", Vec2::new(256., 0.), UVec2::splat(16)),
+                ("Level_2", Vec2::new(0., 256.), UVec2::new(16, 9)),
+            ],
+        );
+        assert!(levels
+            .iter()
+            .all(|level| level.tile_size == Vec2::splat(16.)));
+
+        // Walls and water are solid, and grass is navigable
+        assert_eq!(
+            rows(&levels[2]),
+            [
+                "####..##########",
+                "#..............#",
+                "#..............#",
+                "#..######......#",
+                "#.......#......#",
+                "#.......#..###.#",
+                "#.......#..###.#",
+                "#..............#",
+                "################",
+            ],
+        );
+        assert_eq!(rows(&levels[0])[11], "#..............#");
+    }
+
+    #[test]
+    fn levels_line_up_in_the_world() {
+        let mut levels = world();
+        for y_up in [false, true] {
+            for level in &mut levels {
+                level.y_up = y_up;
+            }
+
+            // The gap in the bottom wall of `Level_0` leads into `Level_2`, below it in LDtk
+            let gap = Vec2::new(72., 256.);
+            let (above, below) = (Vec2::new(0., -8.), Vec2::new(0., 8.));
+            for (level, pixel) in [(&levels[0], gap + above), (&levels[2], gap + below)] {
+                let point = level.from_pixel(pixel);
+                assert_eq!(level.to_pixel(point), pixel);
+                assert!(level.contains(point));
+
+                let navmeshes = level.navmeshes([0.]).unwrap();
+                let mesh = navmeshes.mesh(0.).unwrap();
+                assert_eq!(mesh.distance_to_mesh(point, NavQuery::Accuracy), Some(0.));
+                let wall = level.from_pixel(pixel + Vec2::new(-16., 0.));
+                assert!(mesh.distance_to_mesh(wall, NavQuery::Accuracy).unwrap() > 7.);
+            }
+
+            let up = levels[0].from_pixel(Vec2::ZERO) - levels[0].from_pixel(Vec2::Y);
+            assert_eq!(up.y > 0., y_up);
+        }
+    }
+
+    #[test]
+    fn linear_layouts_place_levels_end_to_end() {
+        let ldtk = project("LinearVertical", "IntGrid", "0, 1", &[(500, 500), (0, 0)]);
+        let levels = parse_levels(&ldtk, Path::new(""), &navability()).unwrap();
+        assert_eq!(levels[0].offset, Vec2::new(0., 4.));
+        assert_eq!(levels[1].offset, Vec2::new(0., 12.));
+        assert_eq!(rows(&levels[0]), [".#"]);
+
+        let ldtk = project("Free", "IntGrid", "0, 1", &[(500, 500)]);
+        let levels = parse_levels(&ldtk, Path::new(""), &navability()).unwrap();
+        assert_eq!(levels[0].offset, Vec2::new(500., 504.));
+    }
+
+    #[test]
+    fn malformed_layers_are_rejected() {
+        for ldtk in [
+            project("Free", "Entities", "0, 1", &[(0, 0)]),
+            project("Free", "IntGrid", "0", &[(0, 0)]),
+        ] {
+            assert!(matches!(
+                parse_levels(&ldtk, Path::new(""), &navability()),
+                Err(LdtkError::Invalid(_)),
+            ));
+        }
+    }
+}
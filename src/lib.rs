@@ -3,15 +3,44 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "bevy")]
+mod avoid;
+#[cfg(feature = "bevy")]
+mod crowd;
+#[cfg(feature = "gizmos")]
+pub mod debug;
+#[cfg(feature = "bevy")]
+mod event;
+pub mod export;
+#[cfg(feature = "bevy")]
+mod flow;
+#[cfg(feature = "bevy")]
+mod formation;
+pub mod graph;
+pub mod grid;
+pub mod hierarchy;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
 pub mod mesh;
 #[cfg(feature = "bevy")]
 mod nav;
 #[cfg(feature = "bevy")]
+mod patrol;
+#[cfg(feature = "bevy")]
 mod plugin;
 #[cfg(feature = "bevy")]
 mod pos;
+mod search;
 #[cfg(feature = "bevy")]
 pub mod set;
+#[cfg(feature = "bevy")]
+mod stuck;
+#[cfg(all(test, feature = "bevy"))]
+mod testing;
+#[cfg(feature = "tiled")]
+pub mod tiled;
 mod vertex;
 
 /// Module for convenient imports. Use with `use seldom_map_nav::prelude::*;`.
@@ -26,12 +55,26 @@ pub mod prelude {
         bevy_reflect::prelude::*, bevy_time::prelude::*, bevy_transform::prelude::*,
     };
 
-    pub use crate::mesh::{Navability, Navmeshes};
+    #[cfg(feature = "gizmos")]
+    pub use crate::debug::{MapNavDebugPlugin, NavGizmos};
     #[cfg(feature = "bevy")]
     pub use crate::{
-        nav::{Nav, NavBundle, PathTarget, Pathfind},
-        plugin::MapNavPlugin,
-        pos::Position2,
+        avoid::Avoidance,
+        crowd::{Crowding, NeighborIndex},
+        event::{Arrived, Drifted, PathError, PathFailed, PathFound, Stuck, WaypointReached},
+        flow::{Flow, FollowFlow},
+        formation::{Formation, FormationMember, FormationShape},
+        nav::{
+            Kinematics, Nav, NavBundle, PathBudget, PathTarget, Pathfind, RepathPolicy, VelocityNav,
+        },
+        patrol::{Patrol, PatrolMode, PatrolStop},
+        plugin::{MapNavPlugin, NavVelocityPlugin},
+        pos::{Position2, Velocity2},
+        stuck::{StuckDetection, StuckRecovery},
+    };
+    pub use crate::{
+        grid::{Connectivity, CornerCutting, GridSettings, TileGrid},
+        mesh::{Navability, Navmeshes, PathMesh},
     };
     pub use navmesh::{NavPathMode, NavQuery};
 }
@@ -2,31 +2,204 @@
 
 #[cfg(feature = "bevy")]
 use crate::prelude::*;
-use crate::vertex::{Ordinal, VertexNormal};
+use crate::{
+    graph::{NavEdge, NavGraph},
+    hierarchy::NavHierarchy,
+    vertex::{Ordinal, VertexNormal},
+};
 
 #[cfg(feature = "bevy")]
 use bevy_platform::collections::HashMap;
 use cdt::triangulate_with_edges;
-use glam::{UVec2, Vec2};
+use glam::{UVec2, Vec2, Vec3};
 use mint::Vector3;
-use navmesh::NavMesh;
+use navmesh::{NavMesh, NavPathMode, NavQuery, NavVec3};
 #[cfg(not(feature = "bevy"))]
 use std::collections::HashMap;
 use std::{
     error::Error,
     fmt::{self, Debug, Display, Formatter},
+    sync::{Arc, OnceLock},
 };
 
-#[derive(Clone, Debug)]
-struct NavmeshEntry {
-    navmesh: NavMesh,
+/// A mesh that can answer path and point queries. Implement this to pathfind over your own
+/// graph type instead of the `navmesh` crate's [`NavMesh`].
+pub trait PathMesh: Send + Sync + 'static {
+    /// Find a path from `from` to `to`. The returned path should start at `from`.
+    /// Backends may ignore the `query` and `mode` quality settings.
+    fn find_path(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        query: NavQuery,
+        mode: NavPathMode,
+    ) -> Option<Vec<Vec2>>;
+
+    /// Find the navigable point closest to `point`
+    fn closest_point(&self, point: Vec2, query: NavQuery) -> Option<Vec2>;
+
+    /// Find the node of [`PathMesh::graph`] that contains the navigable point closest
+    /// to `point`
+    fn node(&self, point: Vec2, query: NavQuery) -> Option<usize>;
+
+    /// How far `point` is from the navigable area. Zero if it's on the mesh. By default, the
+    /// distance to [`PathMesh::closest_point`].
+    fn distance_to_mesh(&self, point: Vec2, query: NavQuery) -> Option<f32> {
+        self.closest_point(point, query)
+            .map(|closest| closest.distance(point))
+    }
+
+    /// Build the graph of nodes, such as triangles or tiles, that paths travel through
+    fn graph(&self) -> NavGraph;
+
+    /// Outlines of the mesh's nodes, such as triangles or tiles, for drawing. By default, there
+    /// are none.
+    fn polygons(&self) -> Vec<Vec<Vec2>> {
+        Vec::new()
+    }
+
+    /// Whether a navigator can travel in a straight line from `from` to `to`. By default,
+    /// checks whether the path between them is straight.
+    fn line_of_sight(&self, from: Vec2, to: Vec2, query: NavQuery) -> bool {
+        self.find_path(from, to, query, NavPathMode::Accuracy)
+            .is_some_and(|path| path.len() <= 2)
+    }
+}
+
+impl PathMesh for NavMesh {
+    fn find_path(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        query: NavQuery,
+        mode: NavPathMode,
+    ) -> Option<Vec<Vec2>> {
+        Some(
+            self.find_path(to_nav(from), to_nav(to), query, mode)?
+                .into_iter()
+                .map(from_nav)
+                .collect(),
+        )
+    }
+
+    fn closest_point(&self, point: Vec2, query: NavQuery) -> Option<Vec2> {
+        self.closest_point(to_nav(point), query).map(from_nav)
+    }
+
+    fn node(&self, point: Vec2, query: NavQuery) -> Option<usize> {
+        self.find_closest_triangle(to_nav(point), query)
+    }
+
+    fn polygons(&self) -> Vec<Vec<Vec2>> {
+        let vertices = self.vertices();
+        self.triangles()
+            .iter()
+            .map(|triangle| {
+                [triangle.first, triangle.second, triangle.third]
+                    .map(|vertex| from_nav(vertices[vertex as usize]))
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    fn graph(&self) -> NavGraph {
+        let vertices = self
+            .vertices()
+            .iter()
+            .copied()
+            .map(from_nav)
+            .collect::<Vec<_>>();
+        let triangles = self
+            .triangles()
+            .iter()
+            .map(|triangle| [triangle.first, triangle.second, triangle.third])
+            .collect::<Vec<_>>();
+        let centers = triangles
+            .iter()
+            .map(|triangle| {
+                triangle
+                    .map(|vertex| vertices[vertex as usize])
+                    .iter()
+                    .sum::<Vec2>()
+                    / 3.
+            })
+            .collect::<Vec<_>>();
+
+        let mut edges = vec![Vec::new(); triangles.len()];
+        let mut sides = HashMap::<(u32, u32), usize>::default();
+        for (index, triangle) in triangles.iter().enumerate() {
+            for side in 0..3 {
+                let (v1, v2) = (triangle[side], triangle[(side + 1) % 3]);
+                let Some(other) = sides.remove(&(v1.max(v2), v1.min(v2))) else {
+                    sides.insert((v1.max(v2), v1.min(v2)), index);
+                    continue;
+                };
+
+                let portal = (vertices[v1 as usize] + vertices[v2 as usize]) / 2.;
+                let cost = centers[index].distance(portal) + portal.distance(centers[other]);
+                edges[index].push(NavEdge {
+                    to: other,
+                    portal,
+                    cost,
+                });
+                edges[other].push(NavEdge {
+                    to: index,
+                    portal,
+                    cost,
+                });
+            }
+        }
+
+        NavGraph::new(centers.into_iter().zip(edges))
+    }
+}
+
+fn to_nav(point: Vec2) -> NavVec3 {
+    Vector3::from(point.extend(0.)).into()
+}
+
+fn from_nav(point: NavVec3) -> Vec2 {
+    Vec3::from(Vector3::from(point)).truncate()
+}
+
+// Shared, so that `Navmeshes` can be cheaply cloned into pathfinding tasks
+#[derive(Debug)]
+struct NavmeshEntry<M> {
+    navmesh: Arc<M>,
     clearance: f32,
+    hierarchy: Option<Arc<NavHierarchy>>,
+    graph: Arc<OnceLock<NavGraph>>,
 }
 
-/// Put this component on your tilemap. Stores your map's navmeshes.
+impl<M> Clone for NavmeshEntry<M> {
+    fn clone(&self) -> Self {
+        Self {
+            navmesh: self.navmesh.clone(),
+            clearance: self.clearance,
+            hierarchy: self.hierarchy.clone(),
+            graph: self.graph.clone(),
+        }
+    }
+}
+
+/// Put this component on your tilemap. Stores your map's navmeshes. The type parameter
+/// is the [`PathMesh`] used to find paths. Cloning is cheap, since clones share their meshes.
+/// When this component changes, paths on the map are found again.
 #[cfg_attr(feature = "bevy", derive(Component))]
-#[derive(Clone, Debug)]
-pub struct Navmeshes(Vec<NavmeshEntry>);
+#[derive(Debug)]
+pub struct Navmeshes<M: PathMesh = NavMesh> {
+    navmeshes: Vec<NavmeshEntry<M>>,
+    changed_region: Option<(Vec2, Vec2)>,
+}
+
+impl<M: PathMesh> Clone for Navmeshes<M> {
+    fn clone(&self) -> Self {
+        Self {
+            navmeshes: self.navmeshes.clone(),
+            changed_region: self.changed_region,
+        }
+    }
+}
 
 impl Navmeshes {
     /// Generate navmeshes for your tilemap. The input to `navability` is a tile's position.
@@ -37,40 +210,145 @@ impl Navmeshes {
         navability: impl Fn(UVec2) -> Navability,
         clearances: impl IntoIterator<Item = f32>,
     ) -> Result<Self, NavmeshGenError> {
-        let mut clearances = clearances.into_iter().collect::<Vec<_>>();
-        clearances.sort_by(f32::total_cmp);
-
-        let mut navmeshes = Vec::with_capacity(clearances.len());
+        let mut navmeshes = Vec::default();
         for clearance in clearances {
-            navmeshes.push(NavmeshEntry {
-                navmesh: generate_navmesh(map_size, tile_size, &navability, clearance)?,
+            navmeshes.push((
+                clearance,
+                generate_navmesh(map_size, tile_size, &navability, clearance)?,
+            ));
+        }
+
+        Ok(Self::new(navmeshes))
+    }
+}
+
+impl<M: PathMesh> Navmeshes<M> {
+    /// Create navmeshes from meshes you have built yourself, paired with their clearances.
+    /// The meshes will be sorted for you.
+    pub fn new(navmeshes: impl IntoIterator<Item = (f32, M)>) -> Self {
+        let mut navmeshes = navmeshes
+            .into_iter()
+            .map(|(clearance, navmesh)| NavmeshEntry {
+                navmesh: Arc::new(navmesh),
                 clearance,
-            });
+                hierarchy: None,
+                graph: Arc::new(OnceLock::new()),
+            })
+            .collect::<Vec<_>>();
+        navmeshes.sort_by(|entry1, entry2| entry1.clearance.total_cmp(&entry2.clearance));
+
+        Self {
+            navmeshes,
+            changed_region: None,
+        }
+    }
+
+    /// Mark the rectangle from `min` to `max` as the only part of the map that differs from
+    /// the navmeshes these replace. When these replace the map's navmeshes, only paths
+    /// that cross the region are found again. Otherwise, every path on the map is.
+    pub fn with_changed_region(mut self, min: Vec2, max: Vec2) -> Self {
+        self.changed_region = Some((min.min(max), min.max(max)));
+        self
+    }
+
+    /// Gets the region set by [`Navmeshes::with_changed_region`], as its minimum
+    /// and maximum corners
+    pub fn changed_region(&self) -> Option<(Vec2, Vec2)> {
+        self.changed_region
+    }
+
+    /// Precompute a [`NavHierarchy`] for each navmesh, so that long paths are planned
+    /// through clusters of `cluster_size` tiles before being refined. `map_size`
+    /// and `tile_size` should match the tilemap the navmeshes cover.
+    pub fn with_hierarchy(mut self, map_size: UVec2, tile_size: Vec2, cluster_size: UVec2) -> Self {
+        for entry in &mut self.navmeshes {
+            entry.hierarchy = Some(Arc::new(NavHierarchy::generate(
+                &*entry.navmesh,
+                map_size,
+                tile_size,
+                cluster_size,
+            )));
         }
 
-        Ok(Self(navmeshes))
+        self
     }
 
     /// Gets the navmesh with the least amount of clearance
     /// greater than or equal to the given clearance
-    pub fn mesh(&self, clearance: f32) -> Option<&NavMesh> {
-        let Navmeshes(navmeshes) = self;
-        navmeshes
-            .get(navmeshes.partition_point(|navmesh| clearance > navmesh.clearance))
-            .map(|navmesh| &navmesh.navmesh)
+    pub fn mesh(&self, clearance: f32) -> Option<&M> {
+        self.entry(clearance).map(|navmesh| &*navmesh.navmesh)
+    }
+
+    /// Gets the clearance of the navmesh that [`Navmeshes::mesh`] returns for the given
+    /// clearance
+    pub fn clearance(&self, clearance: f32) -> Option<f32> {
+        self.entry(clearance).map(|navmesh| navmesh.clearance)
+    }
+
+    /// Gets the hierarchy of the navmesh that [`Navmeshes::mesh`] returns for the given
+    /// clearance, if it has one
+    pub fn hierarchy(&self, clearance: f32) -> Option<&NavHierarchy> {
+        self.entry(clearance)
+            .and_then(|navmesh| navmesh.hierarchy.as_deref())
+    }
+
+    /// Gets the graph of the navmesh that [`Navmeshes::mesh`] returns for the given clearance.
+    /// The graph is built the first time it is requested.
+    pub fn graph(&self, clearance: f32) -> Option<&NavGraph> {
+        self.entry(clearance)
+            .map(|navmesh| navmesh.graph.get_or_init(|| navmesh.navmesh.graph()))
     }
 
     /// Gets a navmesh at the given index. Navmeshes are sorted from least to most clearance.
-    pub fn mesh_at(&self, mesh: usize) -> Option<&NavMesh> {
-        let Navmeshes(navmeshes) = self;
-        navmeshes.get(mesh).map(|entry| &entry.navmesh)
+    pub fn mesh_at(&self, mesh: usize) -> Option<&M> {
+        self.navmeshes.get(mesh).map(|entry| &*entry.navmesh)
+    }
+
+    /// Gets the clearance of the navmesh at the given index
+    pub fn clearance_at(&self, mesh: usize) -> Option<f32> {
+        self.navmeshes.get(mesh).map(|entry| entry.clearance)
     }
 
     /// Gets the number of navmeshes
     pub fn mesh_count(&self) -> usize {
-        let Navmeshes(navmeshes) = self;
-        navmeshes.len()
+        self.navmeshes.len()
+    }
+
+    fn entry(&self, clearance: f32) -> Option<&NavmeshEntry<M>> {
+        let navmeshes = &self.navmeshes;
+        navmeshes.get(navmeshes.partition_point(|navmesh| clearance > navmesh.clearance))
+    }
+}
+
+/// Finds the sides of the given polygons that are not shared with another polygon, such as
+/// the edges of a navmesh from [`PathMesh::polygons`]
+pub fn boundary_edges(polygons: &[Vec<Vec2>]) -> Vec<[Vec2; 2]> {
+    let key = |point: Vec2| (point.x.to_bits(), point.y.to_bits());
+    let mut sides = HashMap::<_, ([Vec2; 2], usize)>::default();
+    for polygon in polygons {
+        for (index, &start) in polygon.iter().enumerate() {
+            let end = polygon[(index + 1) % polygon.len()];
+            let (start_key, end_key) = (key(start), key(end));
+            sides
+                .entry((start_key.min(end_key), start_key.max(end_key)))
+                .or_insert(([start, end], 0))
+                .1 += 1;
+        }
     }
+
+    let mut edges = sides
+        .into_values()
+        .filter(|&(_, count)| count == 1)
+        .map(|(side, _)| side)
+        .collect::<Vec<_>>();
+    // Keep the output stable, regardless of hashing
+    edges.sort_by(|[start1, end1], [start2, end2]| {
+        (start1.x, start1.y, end1.x, end1.y)
+            .partial_cmp(&(start2.x, start2.y, end2.x, end2.y))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    edges
 }
 
 /// Represents the conditions under which this tile is navigable. More variants
@@ -1,33 +1,250 @@
-use std::{collections::VecDeque, error::Error, time::Duration};
+use std::{collections::VecDeque, marker::PhantomData, time::Duration};
 
-use mint::Vector3;
+use bevy_ecs::{
+    archetype::Archetypes,
+    component::{ComponentId, Components, HookContext},
+    entity::{Entities, EntityHashMap},
+    system::SystemParam,
+    world::DeferredWorld,
+};
+use bevy_platform::time::Instant;
+use bevy_tasks::{futures::check_ready, AsyncComputeTaskPool, Task, TaskPool};
 use navmesh::{NavPathMode, NavQuery};
 
-use crate::{prelude::*, set::MapNavSet};
+use crate::{avoid, crowd, graph::NavGraph, mesh::PathMesh, prelude::*, set::MapNavSet};
 
-pub(crate) fn plug<P: Position2>(app: &mut App) {
-    app.add_systems(
-        Update,
-        (ApplyDeferred, generate_paths::<P>, nav::<P>)
-            .chain()
-            .in_set(MapNavSet),
-    );
+// How far apart two points can be and still count as the same point
+pub(crate) const TOLERANCE: f32 = 0.001;
+
+pub(crate) fn plug<P: Position2, M: PathMesh>(app: &mut App) {
+    // Navigation is shared between all `PathMesh`es with the same position component
+    if !app.is_plugin_added::<NavPlugin<P>>() {
+        app.add_plugins(NavPlugin::<P>(PhantomData));
+    }
+
+    let component = app.world_mut().register_component::<Navmeshes<M>>();
+    let mut navmesh_components = app
+        .init_resource::<NavmeshComponents>()
+        .world_mut()
+        .resource_mut::<NavmeshComponents>();
+    if !navmesh_components.0.contains(&component) {
+        navmesh_components.0.push(component);
+    }
+
+    app.add_systems(Update, generate_paths::<P, M>.in_set(NavStep::Path));
+}
+
+// Components of the `Navmeshes` of each `PathMesh` with a `MapNavPlugin`, in the order
+// the plugins were added
+#[derive(Default, Resource)]
+pub(crate) struct NavmeshComponents(Vec<ComponentId>);
+
+// Decides which `MapNavPlugin` handles maps without `Navmeshes<M>`
+#[derive(SystemParam)]
+pub(crate) struct OtherMaps<'w, M: PathMesh> {
+    entities: &'w Entities,
+    archetypes: &'w Archetypes,
+    components: &'w Components,
+    navmesh_components: Res<'w, NavmeshComponents>,
+    mesh: PhantomData<M>,
+}
+
+impl<M: PathMesh> OtherMaps<'_, M> {
+    // Whether a map without `Navmeshes<M>` is left to another `MapNavPlugin`. That's if
+    // another `PathMesh`'s `Navmeshes` are on it. If none are, the first plugin reports it.
+    pub(crate) fn skip(&self, map: Entity) -> bool {
+        let navmesh_components = &self.navmesh_components.0;
+        let other = self.entities.get(map).is_some_and(|location| {
+            let archetype = &self.archetypes[location.archetype_id];
+            navmesh_components
+                .iter()
+                .any(|&component| archetype.contains(component))
+        });
+
+        other
+            || navmesh_components.first().copied() != self.components.component_id::<Navmeshes<M>>()
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SystemSet)]
+pub(crate) enum NavStep {
+    Path,
+    Nav,
+    Avoid,
+    Progress,
+}
+
+struct NavPlugin<P: Position2>(PhantomData<P>);
+
+impl<P: Position2> Plugin for NavPlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PathBudget>()
+            .init_resource::<NeighborIndex<P>>()
+            .configure_sets(
+                Update,
+                (
+                    NavStep::Path,
+                    NavStep::Nav,
+                    NavStep::Avoid,
+                    NavStep::Progress,
+                )
+                    .chain()
+                    .in_set(MapNavSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    ApplyDeferred.before(NavStep::Path).in_set(MapNavSet),
+                    detect_drift::<P>.before(NavStep::Path).in_set(MapNavSet),
+                    crowd::index_neighbors::<P>
+                        .after(NavStep::Path)
+                        .before(NavStep::Nav)
+                        .in_set(MapNavSet),
+                    nav::<P>.in_set(NavStep::Nav),
+                    (crowd::crowd::<P>, avoid::avoid::<P>)
+                        .chain()
+                        .in_set(NavStep::Avoid),
+                    progress::<P>.in_set(NavStep::Progress),
+                ),
+            );
+    }
 }
 
 /// A target to navigate to
-#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[derive(Clone, Debug, PartialEq, Reflect)]
 pub enum PathTarget {
     /// A position
     Static(Vec2),
     /// An entity that has a position
     Dynamic(Entity),
+    /// The nearest of some positions
+    Points(Vec<Vec2>),
+    /// The nearest of some entities that have positions. Entities that no longer have
+    /// a position are skipped.
+    Entities(Vec<Entity>),
+    /// Anywhere in an area, in world units
+    Area(Rect),
+    /// Away from a position. Navigates to the reachable point furthest from it, among those
+    /// that cost at most `budget` to reach. Not supported by [`Flow`].
+    Flee {
+        /// Position to flee from
+        from: Vec2,
+        /// Greatest cost, roughly in world units, of the paths to consider
+        budget: f32,
+    },
+    /// Away from an entity that has a position. See [`PathTarget::Flee`].
+    Evade {
+        /// Entity to flee from
+        from: Entity,
+        /// Greatest cost, roughly in world units, of the paths to consider
+        budget: f32,
+    },
+}
+
+impl PathTarget {
+    // Looks up the positions of the target's entities, so the rest of the search doesn't need
+    // the world
+    pub(crate) fn goal<P: Position2>(&self, positions: &Query<&P>) -> Result<Goal, PathError> {
+        let goal = match self {
+            PathTarget::Static(target) => Goal::Points(vec![(0, *target)]),
+            PathTarget::Dynamic(target) => Goal::Points(vec![(0, position(positions, *target)?)]),
+            PathTarget::Points(targets) => {
+                Goal::Points(targets.iter().copied().enumerate().collect())
+            }
+            PathTarget::Entities(targets) => Goal::Points(
+                targets
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, &target)| Some((index, positions.get(target).ok()?.get())))
+                    .collect(),
+            ),
+            PathTarget::Area(area) => Goal::Area(*area),
+            PathTarget::Flee { from, budget } => Goal::Flee {
+                threat: *from,
+                budget: *budget,
+            },
+            PathTarget::Evade { from, budget } => Goal::Flee {
+                threat: position(positions, *from)?,
+                budget: *budget,
+            },
+        };
+
+        match goal {
+            Goal::Points(ref points) if points.is_empty() => Err(PathError::NoTargets),
+            goal => Ok(goal),
+        }
+    }
+}
+
+fn position<P: Position2>(positions: &Query<&P>, entity: Entity) -> Result<Vec2, PathError> {
+    positions
+        .get(entity)
+        .map(|position| position.get())
+        .map_err(|_| PathError::MissingTarget(entity))
+}
+
+// Current position of the entity that the path leads to, if it leads to an entity
+fn tracked<P: Position2>(pathfind: &Pathfind, positions: &Query<&P>) -> Option<Vec2> {
+    let target = match &pathfind.target {
+        PathTarget::Dynamic(target) => *target,
+        PathTarget::Entities(targets) => *targets.get(pathfind.chosen_target?)?,
+        _ => return None,
+    };
+
+    positions.get(target).ok().map(|position| position.get())
+}
+
+// A `PathTarget` with its entities' positions looked up
+pub(crate) enum Goal {
+    Points(Vec<(usize, Vec2)>),
+    Area(Rect),
+    Flee { threat: Vec2, budget: f32 },
+}
+
+impl Goal {
+    // Resolves each position this goal could be reached at, paired with its index
+    // in the target. An area is reached at any node of the graph that lies inside it.
+    pub(crate) fn points(
+        &self,
+        mesh: &impl PathMesh,
+        graph: &NavGraph,
+        query: NavQuery,
+    ) -> Result<Vec<(usize, Vec2)>, PathError> {
+        match self {
+            Goal::Points(points) => Ok(points.clone()),
+            Goal::Area(area) => {
+                // Nodes without edges, such as closed tiles of a `TileGrid`, can't be reached
+                let nodes = (0..graph.node_count())
+                    .filter(|&node| !graph.edges(node).is_empty())
+                    .map(|node| graph.position(node))
+                    .filter(|&node| area.contains(node))
+                    .enumerate()
+                    .collect::<Vec<_>>();
+                if !nodes.is_empty() {
+                    return Ok(nodes);
+                }
+
+                // Small areas might not contain any nodes
+                let center = area.center();
+                match mesh.distance_to_mesh(center, query) {
+                    Some(distance) if distance <= TOLERANCE => Ok(vec![(0, center)]),
+                    _ => Err(PathError::TargetOffMesh),
+                }
+            }
+            // Fleeing targets do not have fixed positions
+            Goal::Flee { .. } => Err(PathError::UnsupportedTarget),
+        }
+    }
 }
 
 /// Add this component to your entity to have it generate paths. Works as a state
 /// in `seldom_state`.
 #[derive(Clone, Component, Debug)]
+#[component(on_remove = cancel_path)]
 pub struct Pathfind {
-    /// Tilemap with the [`Navmeshes`] component
+    /// Tilemap with the [`Navmeshes`] component. Paths are generated by the [`MapNavPlugin`]
+    /// whose `PathMesh` matches the map's [`Navmeshes`], so pointing navigators at maps
+    /// with different `PathMesh`es lets you switch pathfinding per navigator.
     pub map: Entity,
     /// Clearance radius
     pub radius: f32,
@@ -37,12 +254,43 @@ pub struct Pathfind {
     pub next_repath: Duration,
     /// Target to navigate to
     pub target: PathTarget,
+    /// Index of the target that the generated path leads to, if the target is
+    /// [`PathTarget::Points`] or [`PathTarget::Entities`]
+    pub chosen_target: Option<usize>,
     /// Generated path
     pub path: VecDeque<Vec2>,
+    /// Waypoints after the end of `path` that have not been refined yet. Only used if
+    /// the map's [`Navmeshes`] have a hierarchy. `path` is extended toward these as
+    /// the entity approaches its end.
+    pub coarse_path: VecDeque<Vec2>,
     /// Quality of querying a point on the navmesh
     pub query: NavQuery,
     /// Quality of finding a path
     pub path_mode: NavPathMode,
+    /// Whether to find paths on the [`AsyncComputeTaskPool`] instead of blocking the frame.
+    /// The path is set once the task finishes. A task is restarted if the target or the map's
+    /// [`Navmeshes`] change before it finishes.
+    pub asynchronous: bool,
+    /// Whether a path has been requested, but not found yet. It may be waiting for
+    /// the [`PathBudget`], or being found asynchronously.
+    pub pending: bool,
+    /// Why the last path could not be found, if it couldn't
+    pub error: Option<PathError>,
+    /// Paths with higher priority are found first, when there are more requests than
+    /// the [`PathBudget`] allows
+    pub priority: i32,
+    /// When to find the path again, besides `repath_frequency`
+    pub repath_policy: RepathPolicy,
+    /// End of the path, where it was planned to lead
+    pub destination: Option<Vec2>,
+    /// Last waypoint of the path that was reached, or where the path started
+    pub previous_waypoint: Option<Vec2>,
+    /// Where the target was when [`RepathPolicy::retarget`] last tried to move the end of
+    /// the path to it. It's only tried again once the target moves.
+    pub retargeted_from: Option<Vec2>,
+    /// Target that the navigator gave up on, with [`StuckRecovery::GiveUp`]. Paths aren't found
+    /// again until the target changes.
+    pub abandoned: Option<PathTarget>,
 }
 
 impl Pathfind {
@@ -61,26 +309,212 @@ impl Pathfind {
             repath_frequency,
             next_repath: Duration::ZERO,
             target,
+            chosen_target: None,
             path: VecDeque::new(),
+            coarse_path: VecDeque::new(),
             query,
             path_mode,
+            asynchronous: false,
+            pending: false,
+            error: None,
+            priority: 0,
+            repath_policy: RepathPolicy::default(),
+            destination: None,
+            previous_waypoint: None,
+            retargeted_from: None,
+            abandoned: None,
+        }
+    }
+}
+
+/// When a [`Pathfind`] should find its path again, besides its `repath_frequency`. By default,
+/// it's only found again when the map's navmeshes change.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct RepathPolicy {
+    /// Find the path again when the target is at least this far from the path's destination.
+    /// Only used with [`PathTarget::Dynamic`] and [`PathTarget::Entities`]. Should be larger
+    /// than the navigator's [`Nav::stopping_distance`].
+    pub target_moved: Option<f32>,
+    /// Find the path again when the navigator is at least this far from its path, such as when
+    /// it's pushed
+    pub deviation: Option<f32>,
+    /// Find the path again when the map's [`Navmeshes`] change. If the new navmeshes have
+    /// a [`Navmeshes::changed_region`], only if the path crosses it, or no path was found.
+    pub navmesh_changed: bool,
+    /// When the target moves, move the end of the path to it instead of finding the path again,
+    /// if it can be reached in a straight line from the path's second-to-last waypoint. Only
+    /// used while following a path, and with
+    /// [`PathTarget::Dynamic`] and [`PathTarget::Entities`].
+    pub retarget: bool,
+}
+
+impl Default for RepathPolicy {
+    fn default() -> Self {
+        Self {
+            target_moved: None,
+            deviation: None,
+            navmesh_changed: true,
+            retarget: false,
         }
     }
 }
 
-/// Add this component and [`Pathfind`] to your entity to have it navigate
+// Path being found on the `AsyncComputeTaskPool`, and the target it was requested for
+#[derive(Component)]
+pub(crate) struct PathTask(Task<Result<FoundPath, PathError>>, PathTarget);
+
+fn cancel_path(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    world.commands().entity(entity).try_remove::<PathTask>();
+}
+
+// Path found for a `Pathfind`, and the index of the target it leads to
+pub(crate) struct FoundPath {
+    path: VecDeque<Vec2>,
+    coarse_path: VecDeque<Vec2>,
+    target: usize,
+}
+
+/// Add this component and [`Pathfind`] or [`FollowFlow`] to your entity to have it navigate
 #[derive(Clone, Component, Copy, Debug, Reflect)]
 pub struct Nav {
     /// Speed by which to navigate
     pub speed: f32,
     /// Whether the entity has navigated to the destination
     pub done: bool,
+    /// How far along the path from the destination to stop, such as a melee unit's range
+    pub stopping_distance: f32,
+    /// How close to the stopping point the entity must be to be done
+    pub arrival_tolerance: f32,
+    /// How close to the stopping point the entity starts braking. It brakes at a constant
+    /// rate, so it comes to a stop at the stopping point. Braking is disabled if this is 0.
+    pub slowdown_radius: f32,
+    /// Limits on how quickly the entity can change its velocity. If `None`, it reaches full
+    /// speed and turns instantly.
+    pub kinematics: Option<Kinematics>,
+    /// Velocity that the entity moved at last frame
+    pub velocity: Vec2,
+    /// Direction the entity is facing. With [`Nav::kinematics`], it turns toward the path
+    /// gradually. Otherwise, it's the direction the entity last moved in.
+    pub facing: Dir2,
+    /// How to tell that the entity is stuck, and what to do about it. Only used with
+    /// [`Pathfind`].
+    pub stuck_detection: Option<StuckDetection>,
 }
 
 impl Nav {
     /// Create a `Nav`
     pub fn new(speed: f32) -> Self {
-        Self { speed, done: false }
+        Self {
+            speed,
+            done: false,
+            stopping_distance: 0.,
+            arrival_tolerance: 0.,
+            slowdown_radius: 0.,
+            kinematics: None,
+            velocity: Vec2::ZERO,
+            facing: Dir2::X,
+            stuck_detection: None,
+        }
+    }
+
+    // How far to travel this frame, given the length of the rest of the path and the direction
+    // to its next waypoint, and whether that arrives
+    pub(crate) fn step(&mut self, remaining: f32, direction: Vec2, delta: f32) -> (f32, bool) {
+        let remaining = (remaining - self.stopping_distance).max(0.);
+        let mut speed = match self.slowdown_radius > 0. {
+            true => self.speed * (remaining / self.slowdown_radius).sqrt().min(1.),
+            false => self.speed,
+        };
+
+        if let Some(Kinematics {
+            max_acceleration,
+            max_deceleration,
+            max_turn_rate,
+        }) = self.kinematics
+        {
+            // Turn toward the path, and slow down while facing away from it
+            if let Ok(direction) = Dir2::new(direction) {
+                let max_turn = max_turn_rate * delta;
+                let turn = self.facing.angle_to(*direction).clamp(-max_turn, max_turn);
+                self.facing = Rot2::radians(turn) * self.facing;
+                speed *= self.facing.dot(*direction).max(0.);
+            }
+
+            // Brake in time to stop at the stopping point
+            speed = speed.min((2. * max_deceleration * remaining).sqrt());
+            let current = self.velocity.length();
+            speed = speed
+                .max(current - max_deceleration * delta)
+                .min(current + max_acceleration * delta);
+        }
+
+        let travel_dist = (speed * delta).min(remaining);
+
+        (
+            travel_dist,
+            remaining - travel_dist <= self.arrival_tolerance,
+        )
+    }
+
+    // Updates the velocity and facing after moving from `from` to `to`
+    pub(crate) fn moved(&mut self, from: Vec2, to: Vec2, delta: f32) {
+        let displacement = to - from;
+        self.velocity = match delta > 0. {
+            true => displacement / delta,
+            false => Vec2::ZERO,
+        };
+
+        if self.kinematics.is_none() {
+            if let Ok(direction) = Dir2::new(displacement) {
+                self.facing = direction;
+            }
+        }
+    }
+}
+
+/// Limits on how quickly a navigator can change its velocity. Navigators still follow their
+/// paths exactly, but speed up and brake gradually, and slow down for sharp turns.
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct Kinematics {
+    /// Most that the speed can increase per second
+    pub max_acceleration: f32,
+    /// Most that the speed can decrease per second
+    pub max_deceleration: f32,
+    /// Most that the facing can turn per second, in radians
+    pub max_turn_rate: f32,
+}
+
+impl Kinematics {
+    /// Create a `Kinematics`
+    pub fn new(max_acceleration: f32, max_deceleration: f32, max_turn_rate: f32) -> Self {
+        Self {
+            max_acceleration,
+            max_deceleration,
+            max_turn_rate,
+        }
+    }
+}
+
+/// Add this component and [`Nav`] to your entity to have it navigate by setting
+/// its [`Velocity2`] component instead of its position, such as to drive a physics body.
+/// Its actual position is read back each frame. Requires a [`NavVelocityPlugin`] for
+/// the velocity component.
+#[derive(Clone, Component, Copy, Debug, Default, Reflect)]
+pub struct VelocityNav {
+    /// How far the entity may end up from where it was expected to move before its path
+    /// is found again
+    pub drift_tolerance: f32,
+    /// Where the entity is expected to be once its velocity is applied
+    pub expected: Option<Vec2>,
+}
+
+impl VelocityNav {
+    /// Create a `VelocityNav`
+    pub fn new(drift_tolerance: f32) -> Self {
+        Self {
+            drift_tolerance,
+            expected: None,
+        }
     }
 }
 
@@ -93,128 +527,1467 @@ pub struct NavBundle {
     pub nav: Nav,
 }
 
-pub(crate) fn generate_paths<P: Position2>(
-    #[cfg(feature = "state")] mut commands: Commands,
+/// Limits how many paths are found each frame, so that many navigators repathing at once
+/// don't spike the frame time. Requests over budget wait in a queue, ordered by
+/// [`Pathfind::priority`], and then by how long they have waited. Each frame a request waits,
+/// its priority rises by 1, so low-priority requests are found eventually. Each
+/// [`MapNavPlugin`] has its own budget of this size. Refining the legs of hierarchical paths
+/// is not limited.
+#[derive(Clone, Copy, Debug, Default, Reflect, Resource)]
+pub struct PathBudget {
+    /// Most paths to find each frame. Asynchronous paths count when their tasks are started.
+    /// At least one path is found each frame.
+    pub max_paths: Option<usize>,
+    /// Most time to spend finding paths each frame. At least one path is found each frame.
+    pub max_time: Option<Duration>,
+}
+
+// Path requests waiting for the `PathBudget`, with the order in which they were made
+// and how many frames they have waited
+#[derive(Default)]
+pub(crate) struct PathQueue {
+    requests: EntityHashMap<(u64, i32)>,
+    next: u64,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate_paths<P: Position2, M: PathMesh>(
+    mut commands: Commands,
     positions: Query<&P>,
-    mut pathfinds: Query<(Entity, &P, &mut Pathfind)>,
+    mut pathfinds: Query<
+        (Entity, &P, &mut Pathfind, Option<&mut PathTask>),
+        Without<FormationMember>,
+    >,
     mut navs: Query<&mut Nav>,
-    meshes: Query<&Navmeshes>,
+    meshes: Query<Ref<Navmeshes<M>>>,
+    other_maps: OtherMaps<M>,
+    budget: Res<PathBudget>,
+    mut queue: Local<PathQueue>,
     time: Res<Time>,
 ) {
-    #[allow(unused_variables)]
-    for (entity, position, mut pathfind) in &mut pathfinds {
-        let repath = pathfind
-            .repath_frequency
-            .map(|repath_frequency| {
-                let repath = pathfind.next_repath <= time.elapsed();
-                if repath {
-                    pathfind.next_repath = time.elapsed() + repath_frequency;
+    for (entity, position, mut pathfind, task) in &mut pathfinds {
+        let navmeshes = meshes.get(pathfind.map);
+        if navmeshes.is_err() && other_maps.skip(pathfind.map) {
+            continue;
+        }
+
+        if let Some(abandoned) = &pathfind.abandoned {
+            if *abandoned == pathfind.target {
+                continue;
+            }
+
+            pathfind.abandoned = None;
+            pathfind.next_repath = Duration::ZERO;
+        }
+
+        let Pathfind {
+            repath_frequency,
+            next_repath,
+            ..
+        } = &mut *pathfind;
+        let mut repath = repath_due(*repath_frequency, next_repath, time.elapsed());
+
+        if let Some(mut task) = task {
+            // A new target or changed navmeshes make the pending path outdated. Otherwise,
+            // the task is left to finish, so repathing often doesn't keep restarting it.
+            let changed = navmeshes
+                .as_ref()
+                .is_ok_and(|navmeshes| navmeshes.is_changed());
+            let found = match changed || task.1 != pathfind.target {
+                true => None,
+                false => match check_ready(&mut task.0) {
+                    Some(found) => Some(found),
+                    None => continue,
+                },
+            };
+
+            commands.entity(entity).remove::<PathTask>();
+            pathfind.pending = false;
+
+            match found {
+                Some(found) => {
+                    set_path(&mut commands, entity, &mut pathfind, found, &mut navs);
+                    continue;
                 }
-                repath
-            })
-            .unwrap_or_else(|| {
-                let path = pathfind.next_repath == Duration::ZERO;
-                if path {
-                    pathfind.next_repath = Duration::MAX;
+                None => repath = true,
+            }
+        }
+
+        let policy = pathfind.repath_policy;
+        if !repath && policy.navmesh_changed {
+            repath = navmeshes.as_ref().is_ok_and(|navmeshes| {
+                navmeshes.is_changed()
+                    && match navmeshes.changed_region() {
+                        // The path might be possible now
+                        _ if pathfind.error.is_some() => true,
+                        Some((min, max)) => crosses(position.get(), &pathfind, min, max),
+                        None => true,
+                    }
+            });
+        }
+
+        if let (false, Some(deviation), Some(previous), Some(&next)) = (
+            repath,
+            policy.deviation,
+            pathfind.previous_waypoint,
+            pathfind.path.front(),
+        ) {
+            repath = distance_to_segment(position.get(), previous, next) >= deviation;
+        }
+
+        let target = match repath || pathfind.error.is_some() {
+            true => None,
+            false => tracked(&pathfind, &positions),
+        };
+        if let (Some(target), Some(destination)) = (target, pathfind.destination) {
+            if policy.retarget
+                && pathfind
+                    .retargeted_from
+                    .is_none_or(|from| from.distance(target) > TOLERANCE)
+                && !pathfind.path.is_empty()
+                && pathfind.coarse_path.is_empty()
+            {
+                // Move the end of the path to the target, if it's in sight
+                pathfind.retargeted_from = Some(target);
+                let from = match pathfind.path.len() {
+                    1 => position.get(),
+                    len => pathfind.path[len - 2],
+                };
+                let retargeted = navmeshes
+                    .as_deref()
+                    .ok()
+                    .and_then(|navmeshes| navmeshes.mesh(pathfind.radius))
+                    .and_then(|mesh| {
+                        // The end of the path is clamped to the navmesh, so compare with
+                        // the clamped target
+                        let to = mesh.closest_point(target, pathfind.query)?;
+                        (to.distance(destination) > TOLERANCE
+                            && mesh.line_of_sight(from, to, pathfind.query))
+                        .then_some(to)
+                    });
+
+                if let (Some(to), Some(end)) = (retargeted, pathfind.path.back_mut()) {
+                    *end = to;
+                    pathfind.destination = Some(to);
                 }
-                path
+            }
+
+            if let Some(threshold) = policy.target_moved {
+                repath = target.distance(pathfind.destination.unwrap_or(destination)) >= threshold;
+            }
+        }
+
+        if !repath && pathfind.path.len() <= 2 && !pathfind.coarse_path.is_empty() {
+            // Refine the next leg of a hierarchical path as the entity approaches it
+            let from = pathfind.path.back().copied().unwrap_or(position.get());
+            let Pathfind {
+                radius,
+                coarse_path,
+                query,
+                path_mode,
+                ..
+            } = &mut *pathfind;
+            let leg = navmeshes
+                .as_deref()
+                .ok()
+                .and_then(|navmeshes| navmeshes.mesh(*radius))
+                .and_then(|mesh| refine(mesh, from, coarse_path, *query, *path_mode));
+
+            match leg {
+                Some(leg) => {
+                    pathfind.path.pop_back();
+                    pathfind.path.extend(leg);
+                }
+                None => repath = true,
+            }
+        }
+
+        if repath {
+            let PathQueue { requests, next } = &mut *queue;
+            requests.entry(entity).or_insert_with(|| {
+                *next += 1;
+                (*next, 0)
             });
+            pathfind.pending = true;
+        }
+    }
+
+    let mut requests = queue
+        .requests
+        .iter()
+        .filter_map(|(&entity, &(order, waited))| {
+            let (.., pathfind, _) = pathfinds.get(entity).ok()?;
+            if pathfind.abandoned.is_some() {
+                // It gave up after the path was requested
+                return None;
+            }
 
-        if !repath {
+            Some((
+                pathfind.priority.saturating_add(waited),
+                order,
+                waited,
+                entity,
+            ))
+        })
+        .collect::<Vec<_>>();
+    requests.sort_unstable_by(|(priority1, order1, ..), (priority2, order2, ..)| {
+        priority2.cmp(priority1).then(order1.cmp(order2))
+    });
+
+    let start = Instant::now();
+    let limit = budget.max_paths.unwrap_or(usize::MAX).max(1);
+    let mut found_count = 0;
+    queue.requests.clear();
+
+    for (_, order, waited, entity) in requests {
+        let over_time = budget
+            .max_time
+            .is_some_and(|max_time| found_count > 0 && start.elapsed() >= max_time);
+        if found_count >= limit || over_time {
+            // Wait for the next frame
+            queue
+                .requests
+                .insert(entity, (order, waited.saturating_add(1)));
             continue;
         }
 
-        let path = || -> Result<VecDeque<Vec2>, Box<dyn Error>> {
-            Ok(meshes
-                .get(pathfind.map)?
-                .mesh(pathfind.radius)
-                .ok_or_else(|| {
-                    format!(
-                        "missing navmesh with clearance of at least {}",
-                        pathfind.radius
-                    )
-                })?
-                .find_path(
-                    Vector3::from(position.get().extend(0.)).into(),
-                    Vector3::from(
-                        match pathfind.target {
-                            PathTarget::Static(target) => target,
-                            PathTarget::Dynamic(target) => positions.get(target)?.get(),
-                        }
-                        .extend(0.),
-                    )
-                    .into(),
-                    pathfind.query,
-                    pathfind.path_mode,
-                )
-                .ok_or("no valid path was found")?
-                .into_iter()
-                .map(|pos| Vec3::from(Vector3::from(pos)).truncate())
-                .collect())
+        let Ok((entity, position, mut pathfind, _)) = pathfinds.get_mut(entity) else {
+            continue;
+        };
+        let navmeshes = meshes.get(pathfind.map);
+        if navmeshes.is_err() && other_maps.skip(pathfind.map) {
+            pathfind.pending = false;
+            continue;
+        }
+
+        found_count += 1;
+        pathfind.pending = false;
+
+        let request = || -> Result<_, PathError> {
+            Ok((
+                navmeshes
+                    .as_deref()
+                    .map_err(|_| PathError::MissingMap(pathfind.map))?,
+                pathfind.target.goal(&positions)?,
+            ))
         }();
+        let Pathfind {
+            radius,
+            query,
+            path_mode,
+            ..
+        } = *pathfind;
+        let from = position.get();
+
+        let found = match request {
+            Ok((navmeshes, goal)) if pathfind.asynchronous => {
+                let navmeshes = navmeshes.clone();
+                let task = AsyncComputeTaskPool::get_or_init(TaskPool::new).spawn(async move {
+                    find_path(&navmeshes, radius, from, &goal, query, path_mode)
+                });
+
+                commands
+                    .entity(entity)
+                    .insert(PathTask(task, pathfind.target.clone()));
+                pathfind.pending = true;
+                continue;
+            }
+            Ok((navmeshes, goal)) => find_path(navmeshes, radius, from, &goal, query, path_mode),
+            Err(error) => Err(error),
+        };
+
+        set_path(&mut commands, entity, &mut pathfind, found, &mut navs);
+    }
+}
+
+// Find a path for a `Pathfind`. Doesn't need the world, so it can run on another thread.
+fn find_path<M: PathMesh>(
+    navmeshes: &Navmeshes<M>,
+    radius: f32,
+    from: Vec2,
+    goal: &Goal,
+    query: NavQuery,
+    path_mode: NavPathMode,
+) -> Result<FoundPath, PathError> {
+    let mesh = navmeshes
+        .mesh(radius)
+        .ok_or(PathError::MissingMesh(radius))?;
+    let graph = navmeshes
+        .graph(radius)
+        .ok_or(PathError::MissingMesh(radius))?;
+    // Navigators may be as far from the mesh as its clearance, since it's shrunk by that much
+    let tolerance = navmeshes.clearance(radius).unwrap_or(radius) + TOLERANCE;
+
+    if let Goal::Area(area) = goal {
+        if area.contains(from) {
+            return Ok(FoundPath {
+                path: vec![from].into(),
+                coarse_path: VecDeque::new(),
+                target: 0,
+            });
+        }
+    }
+
+    let (target, to) = match *goal {
+        Goal::Flee { threat, budget } => (
+            0,
+            flee(mesh, graph, from, threat, budget, tolerance, query)?,
+        ),
+        _ => {
+            let targets = goal.points(mesh, graph, query)?;
+            nearest(mesh, graph, from, &targets, tolerance, query)?
+        }
+    };
+
+    let mut coarse_path = navmeshes
+        .hierarchy(radius)
+        .filter(|hierarchy| hierarchy.is_long(from, to))
+        .and_then(|hierarchy| hierarchy.find_path(mesh, graph, from, to, query))
+        .unwrap_or_else(|| vec![to])
+        .into();
+    let path = refine(mesh, from, &mut coarse_path, query, path_mode).ok_or(PathError::NoRoute)?;
+
+    Ok(FoundPath {
+        path: path.into(),
+        coarse_path,
+        target,
+    })
+}
+
+// Applies a found path, or the failure to find one
+fn set_path(
+    commands: &mut Commands,
+    entity: Entity,
+    pathfind: &mut Pathfind,
+    found: Result<FoundPath, PathError>,
+    navs: &mut Query<&mut Nav>,
+) {
+    #[cfg(feature = "log")]
+    if let Err(error) = &found {
+        warn!("failed to generate path: {error}");
+    }
+    #[cfg(feature = "state")]
+    let failure = found.is_err();
 
-        #[cfg(feature = "log")]
-        if let Err(error) = &path {
-            warn!("failed to generate path: {error}");
+    let multiple = matches!(
+        pathfind.target,
+        PathTarget::Points(_) | PathTarget::Entities(_)
+    );
+    match found {
+        Ok(FoundPath {
+            path,
+            coarse_path,
+            target,
+        }) => {
+            pathfind.destination = coarse_path.back().or(path.back()).copied();
+            pathfind.previous_waypoint = path.front().copied();
+            pathfind.retargeted_from = None;
+            pathfind.path = path;
+            pathfind.coarse_path = coarse_path;
+            pathfind.chosen_target = multiple.then_some(target);
+            pathfind.error = None;
+            commands.trigger_targets(PathFound, entity);
         }
-        #[cfg(feature = "state")]
-        let failure = path.is_err();
-        pathfind.path = path.unwrap_or_default();
+        Err(error) => {
+            pathfind.path.clear();
+            pathfind.coarse_path.clear();
+            pathfind.chosen_target = None;
+            pathfind.destination = None;
+            pathfind.previous_waypoint = None;
+            pathfind.error = Some(error);
+            commands.trigger_targets(PathFailed(error), entity);
+        }
+    }
+
+    let Ok(mut nav) = navs.get_mut(entity) else {
+        return;
+    };
+
+    nav.done = pathfind.path.is_empty();
+
+    #[cfg(feature = "state")]
+    if failure {
+        commands.entity(entity).insert(Done::Failure);
+    }
+}
+
+// Whether it's time to repath, and if so, schedules the next repath
+pub(crate) fn repath_due(
+    repath_frequency: Option<Duration>,
+    next_repath: &mut Duration,
+    now: Duration,
+) -> bool {
+    repath_frequency
+        .map(|repath_frequency| {
+            let repath = *next_repath <= now;
+            if repath {
+                *next_repath = now + repath_frequency;
+            }
+            repath
+        })
+        .unwrap_or_else(|| {
+            let path = *next_repath == Duration::ZERO;
+            if path {
+                *next_repath = Duration::MAX;
+            }
+            path
+        })
+}
+
+// Picks the target that is cheapest to reach through the graph. Targets further than `tolerance`
+// from the mesh are skipped.
+fn nearest<M: PathMesh>(
+    mesh: &M,
+    graph: &NavGraph,
+    from: Vec2,
+    targets: &[(usize, Vec2)],
+    tolerance: f32,
+    query: NavQuery,
+) -> Result<(usize, Vec2), PathError> {
+    let from_node = node(mesh, from, tolerance, query).ok_or(PathError::StartOffMesh)?;
+    let (indices, nodes) = targets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &(_, target))| Some((index, node(mesh, target, tolerance, query)?)))
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+
+    let nearest = match *nodes {
+        [] => return Err(PathError::TargetOffMesh),
+        [_] => 0,
+        _ => graph.nearest(from_node, &nodes).ok_or(PathError::NoRoute)?,
+    };
+
+    Ok(targets[indices[nearest]])
+}
+
+// Finds the reachable point furthest from `threat`, among those that cost at most `budget`
+// to reach. Stays put if no such point is further than `from`.
+fn flee<M: PathMesh>(
+    mesh: &M,
+    graph: &NavGraph,
+    from: Vec2,
+    threat: Vec2,
+    budget: f32,
+    tolerance: f32,
+    query: NavQuery,
+) -> Result<Vec2, PathError> {
+    let from_node = node(mesh, from, tolerance, query).ok_or(PathError::StartOffMesh)?;
+    let (costs, _) = graph.dijkstra_within([(from_node, 0.)], budget);
 
-        let Ok(mut nav) = navs.get_mut(entity) else {
+    Ok((0..graph.node_count())
+        .filter(|&node| costs[node].is_finite())
+        .map(|node| graph.position(node))
+        .chain([from])
+        .max_by(|a, b| {
+            a.distance_squared(threat)
+                .total_cmp(&b.distance_squared(threat))
+        })
+        .unwrap_or(from))
+}
+
+// Finds the node of the mesh at `point`, unless it's further than `tolerance` from the mesh.
+// `PathMesh::node` alone finds the nearest node, however far away it is.
+pub(crate) fn node<M: PathMesh>(
+    mesh: &M,
+    point: Vec2,
+    tolerance: f32,
+    query: NavQuery,
+) -> Option<usize> {
+    mesh.distance_to_mesh(point, query)
+        .filter(|&distance| distance <= tolerance)
+        .and(mesh.node(point, query))
+}
+
+// Find a path to the next leg of a coarse path. Skips a waypoint when possible,
+// so the path does not detour through every cluster's node. Only pops the waypoints
+// that the leg reaches.
+fn refine<M: PathMesh>(
+    mesh: &M,
+    from: Vec2,
+    coarse_path: &mut VecDeque<Vec2>,
+    query: NavQuery,
+    path_mode: NavPathMode,
+) -> Option<Vec<Vec2>> {
+    for skip in (0..coarse_path.len().min(2)).rev() {
+        let to = coarse_path[skip];
+        let Some(leg) = mesh.find_path(from, to, query, path_mode) else {
             continue;
         };
 
-        nav.done = pathfind.path.is_empty();
-
-        #[cfg(feature = "state")]
-        if failure {
-            commands.entity(entity).insert(Done::Failure);
+        // Paths end at the closest point on the mesh to the waypoint
+        let end = mesh.closest_point(to, query).unwrap_or(to);
+        if leg
+            .last()
+            .is_some_and(|last| last.distance(end) <= TOLERANCE)
+        {
+            coarse_path.drain(..=skip);
+            return Some(leg);
         }
     }
+
+    None
 }
 
+#[allow(clippy::type_complexity)]
 fn nav<P: Position2>(
-    #[cfg(feature = "state")] mut commands: Commands,
-    mut navs: Query<(Entity, &mut P, &mut Pathfind, &mut Nav)>,
+    mut commands: Commands,
+    mut navs: Query<(
+        Entity,
+        &mut P,
+        &mut Pathfind,
+        &mut Nav,
+        Option<&mut VelocityNav>,
+        Has<Avoidance>,
+        Has<Crowding>,
+    )>,
     time: Res<Time>,
 ) {
-    #[allow(unused_variables)]
-    for (entity, mut position, mut pathfind, mut nav) in &mut navs {
+    for (entity, mut position, mut pathfind, mut nav, velocity_nav, avoids, crowds) in &mut navs {
         if pathfind.path.is_empty() {
+            nav.velocity = Vec2::ZERO;
+            if let Some(mut velocity_nav) = velocity_nav {
+                velocity_nav.expected = None;
+            }
+
+            // Wait for the rest of a hierarchical path to be refined, or for a pending path
+            if pathfind.coarse_path.is_empty() && !pathfind.pending {
+                #[cfg(feature = "state")]
+                commands.entity(entity).insert(match pathfind.error {
+                    Some(_) => Done::Failure,
+                    None => Done::Success,
+                });
+            }
+
+            continue;
+        }
+
+        // The rest of a hierarchical path's length isn't known until it's refined
+        let start = position.get();
+        let remaining = match pathfind.coarse_path.is_empty() {
+            true => length(start, pathfind.path.iter().copied()),
+            false => f32::INFINITY,
+        };
+        let direction = direction(start, pathfind.path.iter().copied());
+        let delta = time.delta_secs();
+        let (travel_dist, arrived) = nav.step(remaining, direction, delta);
+
+        if avoids || crowds {
+            // Avoidance and crowding move the navigator from here, so its waypoints aren't
+            // reached until `progress` sees where it ends up
+            let pos = travel(start, travel_dist, &mut pathfind.path.clone(), |_| ());
+            nav.moved(start, pos, delta);
+            go_to(&mut position, velocity_nav, pos);
+            continue;
+        }
+
+        let Pathfind {
+            path,
+            previous_waypoint,
+            ..
+        } = &mut *pathfind;
+        let pos = travel(start, travel_dist, path, |waypoint| {
+            *previous_waypoint = Some(waypoint);
+            commands.trigger_targets(WaypointReached { waypoint }, entity)
+        });
+        nav.moved(start, pos, delta);
+
+        if arrived {
+            pathfind.path.clear();
+        }
+
+        if pathfind.path.is_empty() && pathfind.coarse_path.is_empty() {
+            nav.done = true;
+            commands.trigger_targets(Arrived, entity);
             #[cfg(feature = "state")]
             commands.entity(entity).insert(Done::Success);
-            continue;
         }
 
-        let mut pos = position.get();
-        let mut travel_dist = nav.speed * time.delta_secs();
-        let mut dest;
-        let mut dest_dist;
+        go_to(&mut position, velocity_nav, pos);
+    }
+}
 
-        while travel_dist >= {
-            dest = *pathfind.path.front().unwrap();
-            dest_dist = (dest - pos).length();
-            dest_dist
-        } {
-            pos = dest;
-            travel_dist -= dest_dist;
+// Pops the waypoints that navigators with `Avoidance` or `Crowding` reached, once those have moved
+// them, and checks whether they arrived
+#[allow(clippy::type_complexity)]
+fn progress<P: Position2>(
+    mut commands: Commands,
+    mut navs: Query<
+        (Entity, &P, &mut Pathfind, &mut Nav, Option<&VelocityNav>),
+        Or<(With<Avoidance>, With<Crowding>)>,
+    >,
+) {
+    for (entity, position, mut pathfind, mut nav, velocity_nav) in &mut navs {
+        if pathfind.path.is_empty() {
+            continue;
+        }
 
-            pathfind.path.pop_front();
-            if pathfind.path.is_empty() {
+        let pos = velocity_nav
+            .and_then(|velocity_nav| velocity_nav.expected)
+            .unwrap_or(position.get());
+        let Pathfind {
+            path,
+            coarse_path,
+            previous_waypoint,
+            ..
+        } = &mut *pathfind;
+
+        // A waypoint is reached once the navigator is past it, along the leg leading to it.
+        // The last waypoint is reached by arriving.
+        while path.len() > 1 || (!path.is_empty() && !coarse_path.is_empty()) {
+            let waypoint = path[0];
+            let previous = previous_waypoint.unwrap_or(waypoint);
+            if (pos - waypoint).dot(waypoint - previous) < 0. {
                 break;
             }
+
+            path.pop_front();
+            // Paths start at the navigator's position, which isn't traveled to
+            if waypoint != previous {
+                *previous_waypoint = Some(waypoint);
+                commands.trigger_targets(WaypointReached { waypoint }, entity);
+            }
         }
 
-        if pathfind.path.is_empty() {
+        let remaining = length(pos, path.iter().copied()) - nav.stopping_distance;
+        if coarse_path.is_empty() && remaining <= nav.arrival_tolerance + TOLERANCE {
+            path.clear();
             nav.done = true;
+            commands.trigger_targets(Arrived, entity);
             #[cfg(feature = "state")]
             commands.entity(entity).insert(Done::Success);
-        } else {
-            let delta = (dest - pos).normalize() * travel_dist;
-            pos += delta;
         }
+    }
+}
+
+// Moves the navigator to `pos`, or leaves that to its velocity if it has a `VelocityNav`
+pub(crate) fn go_to<P: Position2>(
+    position: &mut Mut<P>,
+    velocity_nav: Option<Mut<VelocityNav>>,
+    pos: Vec2,
+) {
+    match velocity_nav {
+        Some(mut velocity_nav) => velocity_nav.expected = Some(pos),
+        None => position.set(pos),
+    }
+}
+
+// Finds paths again for navigators whose velocity didn't take them where they were expected to go
+fn detect_drift<P: Position2>(
+    mut commands: Commands,
+    mut navs: Query<(Entity, &P, &mut VelocityNav, Option<&mut Pathfind>)>,
+) {
+    for (entity, position, mut velocity_nav, pathfind) in &mut navs {
+        let Some(expected) = velocity_nav.expected.take() else {
+            continue;
+        };
+
+        let distance = position.get().distance(expected);
+        if distance <= velocity_nav.drift_tolerance {
+            continue;
+        }
+
+        if let Some(mut pathfind) = pathfind {
+            pathfind.next_repath = Duration::ZERO;
+        }
+
+        commands.trigger_targets(Drifted { distance }, entity);
+    }
+}
+
+// Reads back the actual velocity, so kinematics build on it
+pub(crate) fn read_velocities<V: Velocity2>(mut navs: Query<(&mut Nav, &V), With<VelocityNav>>) {
+    for (mut nav, velocity) in &mut navs {
+        nav.velocity = velocity.get();
+    }
+}
+
+pub(crate) fn apply_velocities<V: Velocity2>(mut navs: Query<(&Nav, &mut V), With<VelocityNav>>) {
+    for (nav, mut velocity) in &mut navs {
+        velocity.set(nav.velocity);
+    }
+}
+
+// Direction from `pos` to the first waypoint of `path` that isn't at `pos`
+pub(crate) fn direction(pos: Vec2, path: impl IntoIterator<Item = Vec2>) -> Vec2 {
+    path.into_iter()
+        .map(|waypoint| waypoint - pos)
+        .find(|&direction| direction != Vec2::ZERO)
+        .unwrap_or_default()
+}
+
+// Whether the rest of the path, from `pos`, crosses the rectangle from `min` to `max`
+fn crosses(pos: Vec2, pathfind: &Pathfind, min: Vec2, max: Vec2) -> bool {
+    let mut start = pos;
+    let mut waypoints = pathfind.path.iter().chain(&pathfind.coarse_path).copied();
+    if pathfind.path.is_empty() && pathfind.coarse_path.is_empty() {
+        return pos.cmpge(min).all() && pos.cmple(max).all();
+    }
+
+    waypoints.any(|end| {
+        let crossed = segment_crosses(start, end, min, max);
+        start = end;
+        crossed
+    })
+}
+
+// Whether the segment from `start` to `end` crosses the rectangle from `min` to `max`
+fn segment_crosses(start: Vec2, end: Vec2, min: Vec2, max: Vec2) -> bool {
+    let delta = end - start;
+    let (mut enter, mut exit) = (0., 1.);
+
+    for axis in 0..2 {
+        if delta[axis] == 0. {
+            if start[axis] < min[axis] || start[axis] > max[axis] {
+                return false;
+            }
+
+            continue;
+        }
+
+        let t1 = (min[axis] - start[axis]) / delta[axis];
+        let t2 = (max[axis] - start[axis]) / delta[axis];
+        enter = t1.min(t2).max(enter);
+        exit = t1.max(t2).min(exit);
+    }
+
+    enter <= exit
+}
+
+// Distance from `pos` to the segment from `start` to `end`
+fn distance_to_segment(pos: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = match segment.length_squared() {
+        0. => 0.,
+        length_squared => ((pos - start).dot(segment) / length_squared).clamp(0., 1.),
+    };
+
+    pos.distance(start + segment * t)
+}
+
+// Length of the path from `pos` through `path`
+pub(crate) fn length(pos: Vec2, path: impl IntoIterator<Item = Vec2>) -> f32 {
+    path.into_iter()
+        .fold((pos, 0.), |(prev, length), waypoint| {
+            (waypoint, length + prev.distance(waypoint))
+        })
+        .1
+}
+
+// Moves from `pos` along `path` by `travel_dist`, popping waypoints as they are reached.
+// `reached` is called with each waypoint that is traveled to.
+pub(crate) fn travel(
+    mut pos: Vec2,
+    mut travel_dist: f32,
+    path: &mut VecDeque<Vec2>,
+    mut reached: impl FnMut(Vec2),
+) -> Vec2 {
+    while let Some(&dest) = path.front() {
+        let dest_dist = (dest - pos).length();
+        if travel_dist < dest_dist {
+            return pos + (dest - pos) / dest_dist * travel_dist;
+        }
+
+        // Paths start at the navigator's position, which isn't traveled to
+        if dest_dist > 0. {
+            reached(dest);
+        }
+
+        pos = dest;
+        travel_dist -= dest_dist;
+        path.pop_front();
+    }
+
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_time::{TimePlugin, TimeUpdateStrategy};
+
+    use super::*;
+    use crate::testing;
+
+    // 8x8 map with a walled-off room in the top right corner
+    fn grid() -> TileGrid {
+        TileGrid::generate(
+            UVec2::splat(8),
+            Vec2::ONE,
+            |tile| match (tile.x == 5 && tile.y >= 5) || (tile.y == 5 && tile.x >= 5) {
+                true => Navability::Solid,
+                false => Navability::Navable,
+            },
+            0.,
+            GridSettings::default(),
+        )
+    }
+
+    #[test]
+    fn refine_skips_a_coarse_waypoint() {
+        let mut coarse_path = VecDeque::from([Vec2::new(2.5, 0.5), Vec2::new(4.5, 0.5)]);
+        let leg = refine(
+            &grid(),
+            Vec2::new(0.5, 0.5),
+            &mut coarse_path,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy,
+        );
+        assert_eq!(leg.unwrap().last(), Some(&Vec2::new(4.5, 0.5)));
+        assert!(coarse_path.is_empty());
+    }
+
+    #[test]
+    fn refine_only_pops_reached_waypoints() {
+        let mut coarse_path = VecDeque::from([Vec2::new(2.5, 2.5), Vec2::new(6.5, 6.5)]);
+        let leg = refine(
+            &grid(),
+            Vec2::new(0.5, 0.5),
+            &mut coarse_path,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy,
+        );
+        assert_eq!(leg.unwrap().last(), Some(&Vec2::new(2.5, 2.5)));
+        assert_eq!(coarse_path, [Vec2::new(6.5, 6.5)]);
+
+        let leg = refine(
+            &grid(),
+            Vec2::new(2.5, 2.5),
+            &mut coarse_path,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy,
+        );
+        assert_eq!(leg, None);
+        assert_eq!(coarse_path, [Vec2::new(6.5, 6.5)]);
+    }
+
+    #[test]
+    fn areas_are_reached_at_open_nodes() {
+        let grid = grid();
+        let graph = grid.graph();
+        let points = |min, max| {
+            Goal::Area(Rect::from_corners(min, max)).points(&grid, &graph, NavQuery::Accuracy)
+        };
+
+        // Covers the wall tiles from (4, 4) to (5, 5)
+        let reachable = points(Vec2::splat(4.), Vec2::splat(6.)).unwrap();
+        let mut reachable = reachable
+            .into_iter()
+            .map(|(_, point)| point)
+            .collect::<Vec<_>>();
+        reachable.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        assert_eq!(
+            reachable,
+            [
+                Vec2::new(4.5, 4.5),
+                Vec2::new(4.5, 5.5),
+                Vec2::new(5.5, 4.5)
+            ],
+        );
+
+        // Small areas without nodes are reached at their center, if it's open
+        assert_eq!(
+            points(Vec2::new(1.1, 1.1), Vec2::new(1.3, 1.3)),
+            Ok(vec![(0, Vec2::splat(1.2))]),
+        );
+        assert_eq!(
+            points(Vec2::new(5.1, 7.1), Vec2::new(5.3, 7.3)),
+            Err(PathError::TargetOffMesh),
+        );
+    }
+
+    #[test]
+    fn points_off_the_mesh_fail() {
+        let grid = grid();
+        let graph = grid.graph();
+        let (open, wall) = (Vec2::new(0.5, 0.5), Vec2::new(5.5, 6.5));
+        let nearest = |from, to, tolerance| {
+            nearest(
+                &grid,
+                &graph,
+                from,
+                &[(0, to)],
+                tolerance,
+                NavQuery::Accuracy,
+            )
+        };
+
+        assert_eq!(
+            nearest(open, Vec2::new(2.5, 0.5), TOLERANCE),
+            Ok((0, Vec2::new(2.5, 0.5)))
+        );
+        assert_eq!(nearest(wall, open, TOLERANCE), Err(PathError::StartOffMesh));
+        assert_eq!(
+            nearest(open, wall, TOLERANCE),
+            Err(PathError::TargetOffMesh)
+        );
+        // The wall tile is half a tile from the open tiles beside it
+        assert_eq!(nearest(open, wall, 0.5), Ok((0, wall)));
+    }
+
+    #[test]
+    fn maps_without_navmeshes_fail() {
+        let mut app = testing::app();
+        app.add_plugins(MapNavPlugin::<Transform, TileGrid>::default());
+        testing::record::<PathFailed>(&mut app);
+        let grid_map = app.world_mut().spawn(Navmeshes::new([(0., grid())])).id();
+        let empty_map = app.world_mut().spawn_empty().id();
+        let target = PathTarget::Static(Vec2::new(2.5, 0.5));
+        let [on_grid, lost] = [grid_map, empty_map].map(|map| {
+            testing::spawn(
+                &mut app,
+                Vec2::new(0.5, 0.5),
+                (Nav::new(1.), testing::pathfind(map, 0., target.clone())),
+            )
+        });
+
+        app.update();
+        // Only the `TileGrid` plugin paths over the grid
+        let pathfind = testing::get::<Pathfind>(&app, on_grid);
+        assert_eq!(pathfind.error, None);
+        assert!(!pathfind.path.is_empty());
+        assert!(testing::recorded::<PathFailed>(&mut app, on_grid).is_empty());
+        // And only one plugin reports the map without navmeshes
+        assert_eq!(
+            testing::get::<Pathfind>(&app, lost).error,
+            Some(PathError::MissingMap(empty_map))
+        );
+        assert_eq!(testing::recorded::<PathFailed>(&mut app, lost).len(), 1);
+    }
+
+    #[test]
+    fn navigators_follow_paths_of_other_meshes() {
+        let mut app = App::new();
+        app.add_plugins((TimePlugin, MapNavPlugin::<Transform, TileGrid>::default()))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(testing::FRAME));
+        let map = app.world_mut().spawn(Navmeshes::new([(0., grid())])).id();
+        // Into the walled-off room isn't possible
+        let [navigator, walled_off] = [Vec2::new(0.5, 7.5), Vec2::new(6.5, 6.5)].map(|target| {
+            testing::spawn(
+                &mut app,
+                Vec2::new(7.5, 0.5),
+                (
+                    Nav::new(4.),
+                    testing::pathfind(map, 0., PathTarget::Static(target)),
+                ),
+            )
+        });
+
+        testing::run(&mut app, 2);
+        let pathfind = testing::get::<Pathfind>(&app, navigator);
+        // The grid's path goes between tile centers, around the room
+        assert!(pathfind
+            .path
+            .iter()
+            .all(|waypoint| waypoint.fract() == Vec2::splat(0.5)));
+        assert_eq!(
+            testing::get::<Pathfind>(&app, walled_off).error,
+            Some(PathError::NoRoute)
+        );
+
+        testing::run(&mut app, 40);
+        assert_eq!(testing::position(&app, navigator), Vec2::new(0.5, 7.5));
+        assert!(testing::get::<Nav>(&app, navigator).done);
+    }
+
+    #[test]
+    fn fleeing_goes_furthest_within_the_budget() {
+        let grid = grid();
+        let graph = grid.graph();
+        let flee = |budget| {
+            flee(
+                &grid,
+                &graph,
+                Vec2::new(2.5, 2.5),
+                Vec2::new(3.5, 2.5),
+                budget,
+                TOLERANCE,
+                NavQuery::Accuracy,
+            )
+        };
+
+        assert_eq!(flee(2.), Ok(Vec2::new(0.5, 2.5)));
+        // The walled-off room's far corner is further, but can't be reached
+        assert_eq!(flee(100.), Ok(Vec2::new(0.5, 7.5)));
+        // Nowhere is further than staying put
+        assert_eq!(flee(0.), Ok(Vec2::new(2.5, 2.5)));
+    }
+
+    #[test]
+    fn evading_navigators_move_away_from_the_entity() {
+        let mut app = testing::app();
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let threat = app.world_mut().spawn(Transform::from_xyz(5., 4., 0.)).id();
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(4., 4.),
+            (
+                Nav::new(1.),
+                testing::pathfind(
+                    map,
+                    0.,
+                    PathTarget::Evade {
+                        from: threat,
+                        budget: 3.,
+                    },
+                ),
+            ),
+        );
+
+        app.update();
+        let destination = testing::get::<Pathfind>(&app, navigator)
+            .destination
+            .unwrap();
+        assert!(destination.distance(Vec2::new(5., 4.)) > 2.);
+        assert!(destination.distance(Vec2::new(4., 4.)) <= 3. + 1e-3);
+
+        app.world_mut().despawn(threat);
+        testing::get_mut::<Pathfind>(&mut app, navigator).next_repath = Duration::ZERO;
+        app.update();
+        assert_eq!(
+            testing::get::<Pathfind>(&app, navigator).error,
+            Some(PathError::MissingTarget(threat))
+        );
+    }
+
+    // Navigators that PathFound was triggered on, in order
+    fn found(app: &mut App) -> Vec<Entity> {
+        let mut recorded = app
+            .world_mut()
+            .resource_mut::<testing::Recorded<PathFound>>();
+        recorded.0.drain(..).map(|(entity, _)| entity).collect()
+    }
+
+    #[test]
+    fn budgeted_paths_are_found_by_priority() {
+        let mut app = testing::app();
+        testing::record::<PathFound>(&mut app);
+        app.insert_resource(PathBudget {
+            max_paths: Some(1),
+            max_time: None,
+        });
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let [first, urgent, last] = [0, 5, 0].map(|priority| {
+            testing::spawn(
+                &mut app,
+                Vec2::new(0.5, 0.5),
+                (
+                    Nav::new(1.),
+                    Pathfind {
+                        priority,
+                        ..testing::pathfind(map, 0., PathTarget::Static(Vec2::splat(6.5)))
+                    },
+                ),
+            )
+        });
+
+        app.update();
+        assert_eq!(found(&mut app), [urgent]);
+        assert!(testing::get::<Pathfind>(&app, first).pending);
+        // Ties go to the earlier request
+        app.update();
+        assert_eq!(found(&mut app), [first]);
+        app.update();
+        assert_eq!(found(&mut app), [last]);
+        assert!(!testing::get::<Pathfind>(&app, last).pending);
+    }
+
+    #[test]
+    fn waiting_paths_rise_in_priority() {
+        let mut app = testing::app();
+        testing::record::<PathFound>(&mut app);
+        app.insert_resource(PathBudget {
+            max_paths: Some(1),
+            max_time: None,
+        });
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let target = PathTarget::Static(Vec2::splat(6.5));
+        let low = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (Nav::new(1.), testing::pathfind(map, 0., target.clone())),
+        );
+        // Asks for a path every frame
+        let high = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(1.),
+                Pathfind {
+                    priority: 2,
+                    ..Pathfind::new(
+                        map,
+                        0.,
+                        Some(Duration::ZERO),
+                        target,
+                        NavQuery::Accuracy,
+                        NavPathMode::Accuracy,
+                    )
+                },
+            ),
+        );
+
+        testing::run(&mut app, 2);
+        assert_eq!(found(&mut app), [high, high]);
+        app.update();
+        assert_eq!(found(&mut app), [low]);
+        app.update();
+        assert_eq!(found(&mut app), [high]);
+    }
+
+    #[test]
+    fn waypoints_and_arrival_are_reported() {
+        let mut app = testing::app();
+        testing::record::<PathFound>(&mut app);
+        testing::record::<WaypointReached>(&mut app);
+        testing::record::<Arrived>(&mut app);
+        let map = testing::map(
+            &mut app,
+            UVec2::splat(8),
+            |tile| tile.x == 3 && tile.y < 6,
+            [0.],
+        );
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(4.),
+                testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, 0.5))),
+            ),
+        );
+
+        app.update();
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+        let path = testing::get::<Pathfind>(&app, navigator).path.clone();
+        assert!(path.len() >= 3);
+        assert!(testing::recorded::<Arrived>(&mut app, navigator).is_empty());
+
+        testing::run(&mut app, 40);
+        let reached = testing::recorded::<WaypointReached>(&mut app, navigator)
+            .into_iter()
+            .map(|reached| reached.waypoint)
+            .collect::<Vec<_>>();
+        assert_eq!(reached, Vec::from(path));
+        assert_eq!(testing::recorded::<Arrived>(&mut app, navigator).len(), 1);
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+    }
+
+    #[test]
+    fn navigators_stop_short_of_the_target() {
+        let mut nav = Nav {
+            stopping_distance: 1.,
+            ..Nav::new(10.)
+        };
+        assert_eq!(nav.step(3., Vec2::X, 1.), (2., true));
+        assert_eq!(nav.step(0.5, Vec2::X, 1.), (0., true));
+
+        let mut nav = Nav::new(1.);
+        assert_eq!(nav.step(1.5, Vec2::X, 1.), (1., false));
+        nav.arrival_tolerance = 0.5;
+        assert_eq!(nav.step(1.5, Vec2::X, 1.), (1., true));
+    }
+
+    #[test]
+    fn navigators_slow_down_near_the_end() {
+        let mut nav = Nav {
+            slowdown_radius: 4.,
+            ..Nav::new(2.)
+        };
+        assert_eq!(nav.step(10., Vec2::X, 0.1), (0.2, false));
+        assert_eq!(nav.step(4., Vec2::X, 0.1), (0.2, false));
+        assert_eq!(nav.step(1., Vec2::X, 0.1), (0.1, false));
+        // Remaining distance is measured to the stopping point
+        nav.stopping_distance = 1.;
+        assert_eq!(nav.step(2., Vec2::X, 0.1), (0.1, false));
+    }
+
+    #[test]
+    fn stopping_distance_leaves_a_gap_to_the_target() {
+        let mut app = testing::app();
+        testing::record::<Arrived>(&mut app);
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav {
+                    stopping_distance: 1.,
+                    slowdown_radius: 2.,
+                    ..Nav::new(4.)
+                },
+                testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, 0.5))),
+            ),
+        );
+
+        testing::run(&mut app, 40);
+        assert!(testing::position(&app, navigator).distance(Vec2::new(5.5, 0.5)) < 1e-3);
+        assert!(testing::get::<Nav>(&app, navigator).done);
+        assert_eq!(testing::recorded::<Arrived>(&mut app, navigator).len(), 1);
+    }
+
+    #[test]
+    fn kinematics_limit_speeding_up_and_braking() {
+        let mut nav = Nav {
+            kinematics: Some(Kinematics::new(2., 4., 10.)),
+            ..Nav::new(10.)
+        };
+        assert_eq!(nav.step(100., Vec2::X, 0.5), (0.5, false));
+
+        // Moving too fast to stop in time, so it brakes as hard as it can
+        nav.velocity = Vec2::new(10., 0.);
+        assert_eq!(nav.step(2., Vec2::X, 0.5), (2., true));
+        nav.velocity = Vec2::new(10., 0.);
+        assert_eq!(nav.step(5., Vec2::X, 0.5), (4., false));
+        // Slow enough to brake at a constant rate until it stops
+        nav.velocity = Vec2::new(4., 0.);
+        assert_eq!(nav.step(2., Vec2::X, 0.25), (1., false));
+        nav.velocity = Vec2::new(3., 0.);
+        let (travel_dist, _) = nav.step(1., Vec2::X, 0.25);
+        assert!((travel_dist - (2. * 4. * 1_f32).sqrt() * 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn kinematics_limit_turning() {
+        let mut nav = Nav {
+            kinematics: Some(Kinematics::new(100., 100., 1.)),
+            ..Nav::new(1.)
+        };
+        let (travel_dist, _) = nav.step(10., Vec2::Y, 0.5);
+        assert!((nav.facing.to_angle() - 0.5).abs() < 1e-5);
+        // It slows down while facing away from the path
+        assert!((travel_dist - 0.5 * 0.5_f32.sin()).abs() < 1e-5);
+
+        nav.facing = Dir2::NEG_Y;
+        assert_eq!(nav.step(10., Vec2::Y, 0.5).0, 0.);
+    }
+
+    #[test]
+    fn velocity_navigators_set_their_velocity() {
+        let mut app = testing::app();
+        app.add_plugins(NavVelocityPlugin::<testing::Velocity>::default());
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(2.),
+                VelocityNav::new(0.1),
+                testing::Velocity::default(),
+                testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, 0.5))),
+            ),
+        );
+
+        app.update();
+        // Nothing moves the body, so it's where it started
+        assert_eq!(testing::position(&app, navigator), Vec2::new(0.5, 0.5));
+        let velocity = testing::get::<testing::Velocity>(&app, navigator).0;
+        assert!(velocity.distance(Vec2::new(2., 0.)) < 1e-5);
+        let expected = testing::get::<VelocityNav>(&app, navigator).expected;
+        assert!(expected.unwrap().distance(Vec2::new(0.7, 0.5)) < 1e-5);
+
+        app.add_systems(PreUpdate, testing::move_bodies);
+        testing::run(&mut app, 40);
+        assert!(testing::position(&app, navigator).distance(Vec2::new(6.5, 0.5)) < 1e-3);
+        assert_eq!(
+            testing::get::<testing::Velocity>(&app, navigator).0,
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn drifting_too_far_finds_the_path_again() {
+        let mut app = testing::app();
+        app.add_plugins(NavVelocityPlugin::<testing::Velocity>::default())
+            .add_systems(PreUpdate, testing::move_bodies);
+        testing::record::<Drifted>(&mut app);
+        testing::record::<PathFound>(&mut app);
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(1.),
+                VelocityNav::new(0.5),
+                testing::Velocity::default(),
+                testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, 0.5))),
+            ),
+        );
+        testing::run(&mut app, 2);
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+
+        // Pushed, but not too far
+        testing::get_mut::<Transform>(&mut app, navigator)
+            .translation
+            .y += 0.4;
+        app.update();
+        assert!(testing::recorded::<Drifted>(&mut app, navigator).is_empty());
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+
+        testing::get_mut::<Transform>(&mut app, navigator)
+            .translation
+            .y += 2.;
+        app.update();
+        let drifted = testing::recorded::<Drifted>(&mut app, navigator);
+        assert_eq!(drifted.len(), 1);
+        assert!((drifted[0].distance - 2.).abs() < 1e-4);
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+    }
+
+    // Spawns a navigator that stays put, with a path to an entity
+    fn chaser(app: &mut App, policy: RepathPolicy) -> (Entity, Entity) {
+        testing::record::<PathFound>(app);
+        // Wall down from the top, at x = 4
+        let map = testing::map(
+            app,
+            UVec2::splat(8),
+            |tile| tile.x == 4 && tile.y >= 2,
+            [0.],
+        );
+        let target = app
+            .world_mut()
+            .spawn(Transform::from_xyz(2.5, 0.5, 0.))
+            .id();
+        let navigator = testing::spawn(
+            app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(0.),
+                Pathfind {
+                    repath_policy: policy,
+                    ..testing::pathfind(map, 0., PathTarget::Dynamic(target))
+                },
+            ),
+        );
+
+        app.update();
+        assert_eq!(testing::recorded::<PathFound>(app, navigator).len(), 1);
+        (navigator, target)
+    }
+
+    fn move_to(app: &mut App, entity: Entity, pos: Vec2) {
+        testing::get_mut::<Transform>(app, entity).translation = pos.extend(0.);
+        app.update();
+    }
+
+    #[test]
+    fn paths_are_found_again_when_the_target_moves_far_enough() {
+        let mut app = testing::app();
+        let (navigator, target) = chaser(
+            &mut app,
+            RepathPolicy {
+                target_moved: Some(1.),
+                ..RepathPolicy::default()
+            },
+        );
+
+        move_to(&mut app, target, Vec2::new(2.5, 1.));
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+        move_to(&mut app, target, Vec2::new(2.5, 1.5));
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+        let destination = testing::get::<Pathfind>(&app, navigator).destination;
+        assert_eq!(destination, Some(Vec2::new(2.5, 1.5)));
+    }
+
+    #[test]
+    fn paths_are_found_again_when_the_navigator_strays() {
+        let mut app = testing::app();
+        let (navigator, target) = chaser(
+            &mut app,
+            RepathPolicy {
+                deviation: Some(1.),
+                ..RepathPolicy::default()
+            },
+        );
+
+        move_to(&mut app, navigator, Vec2::new(1.5, 1.));
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+        move_to(&mut app, navigator, Vec2::new(1.5, 2.));
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+        // The target wasn't moved, so there's no threshold for it
+        move_to(&mut app, target, Vec2::new(0.5, 7.5));
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+    }
+
+    #[test]
+    fn paths_are_retargeted_in_sight() {
+        let mut app = testing::app();
+        let (navigator, target) = chaser(
+            &mut app,
+            RepathPolicy {
+                retarget: true,
+                ..RepathPolicy::default()
+            },
+        );
+
+        // The end of the path is moved, instead of finding it again
+        move_to(&mut app, target, Vec2::new(2.5, 1.5));
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+        let pathfind = testing::get::<Pathfind>(&app, navigator);
+        assert_eq!(pathfind.path.back(), Some(&Vec2::new(2.5, 1.5)));
+        assert_eq!(pathfind.destination, Some(Vec2::new(2.5, 1.5)));
+
+        // Behind the wall, it can't be
+        move_to(&mut app, target, Vec2::new(6.5, 4.5));
+        let pathfind = testing::get::<Pathfind>(&app, navigator);
+        assert_eq!(pathfind.path.back(), Some(&Vec2::new(2.5, 1.5)));
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+    }
+
+    #[test]
+    fn paths_are_found_again_when_the_navmeshes_change() {
+        let mut app = testing::app();
+        testing::record::<PathFound>(&mut app);
+        let navmeshes = |wall: bool| {
+            Navmeshes::generate(
+                UVec2::splat(8),
+                Vec2::ONE,
+                |tile| match wall && tile == UVec2::new(3, 0) {
+                    true => Navability::Solid,
+                    false => Navability::Navable,
+                },
+                [0.],
+            )
+            .unwrap()
+        };
+        let map = app.world_mut().spawn(navmeshes(false)).id();
+        let spawn = |app: &mut App, y: f32, repath_policy: RepathPolicy| {
+            testing::spawn(
+                app,
+                Vec2::new(0.5, y),
+                (
+                    Nav::new(0.),
+                    Pathfind {
+                        repath_policy,
+                        ..testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, y)))
+                    },
+                ),
+            )
+        };
+        let bottom = spawn(&mut app, 0.5, RepathPolicy::default());
+        let top = spawn(&mut app, 6.5, RepathPolicy::default());
+        let ignoring = spawn(
+            &mut app,
+            0.5,
+            RepathPolicy {
+                navmesh_changed: false,
+                ..RepathPolicy::default()
+            },
+        );
+        app.update();
+        let found = |app: &mut App| {
+            [bottom, top, ignoring]
+                .map(|navigator| testing::recorded::<PathFound>(app, navigator).len())
+        };
+        assert_eq!(found(&mut app), [1, 1, 1]);
+
+        // Only the path through the changed region is found again
+        app.world_mut()
+            .entity_mut(map)
+            .insert(navmeshes(true).with_changed_region(Vec2::new(3., 0.), Vec2::new(4., 1.)));
+        app.update();
+        assert_eq!(found(&mut app), [1, 0, 0]);
+        assert!(testing::get::<Pathfind>(&app, bottom).path.len() > 1);
 
-        position.set(pos);
+        app.world_mut().entity_mut(map).insert(navmeshes(false));
+        app.update();
+        assert_eq!(found(&mut app), [1, 1, 0]);
     }
 }
@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use crate::{nav::NavStep, prelude::*, set::MapNavSet};
+
+pub(crate) fn plug(app: &mut App) {
+    // Patrols don't depend on the position component or `PathMesh`
+    if !app.is_plugin_added::<PatrolPlugin>() {
+        app.add_plugins(PatrolPlugin);
+    }
+}
+
+struct PatrolPlugin;
+
+impl Plugin for PatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(wait_at_stop)
+            .add_observer(skip_failed_stop)
+            .add_systems(Update, patrol.before(NavStep::Path).in_set(MapNavSet));
+    }
+}
+
+/// Add this component to a navigator with [`Pathfind`] to have it navigate to each of
+/// the stops in turn. It sets the [`Pathfind::target`] for each leg. If the path to a stop
+/// fails, or the navigator gives up on it, the stop is skipped.
+#[derive(Clone, Component, Debug, Reflect)]
+pub struct Patrol {
+    /// Stops to navigate to, in order
+    pub stops: Vec<PatrolStop>,
+    /// What to do after the last stop
+    pub mode: PatrolMode,
+    /// Index of the stop being navigated to, or waited at
+    pub current: usize,
+    /// Whether the patrol is over, with [`PatrolMode::Once`]. Set after waiting at the last stop.
+    pub done: bool,
+    // Whether stops are being visited in reverse, with `PatrolMode::PingPong`
+    reverse: bool,
+    // When to leave the current stop, if the navigator is waiting at it
+    leave_at: Option<Duration>,
+}
+
+impl Patrol {
+    /// Create a `Patrol`
+    pub fn new(stops: Vec<PatrolStop>, mode: PatrolMode) -> Self {
+        Self {
+            stops,
+            mode,
+            current: 0,
+            done: false,
+            reverse: false,
+            leave_at: None,
+        }
+    }
+
+    // Moves on to the next stop, if there is one
+    fn advance(&mut self) {
+        let last = self.stops.len().saturating_sub(1);
+        match self.mode {
+            PatrolMode::Loop => self.current = (self.current + 1) % self.stops.len().max(1),
+            PatrolMode::PingPong => {
+                if self.current == last {
+                    self.reverse = true;
+                } else if self.current == 0 {
+                    self.reverse = false;
+                }
+
+                self.current = match self.reverse {
+                    true => self.current.saturating_sub(1),
+                    false => (self.current + 1).min(last),
+                };
+            }
+            PatrolMode::Once => match self.current < last {
+                true => self.current += 1,
+                false => self.done = true,
+            },
+        }
+    }
+}
+
+/// A stop of a [`Patrol`]
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct PatrolStop {
+    /// Target to navigate to
+    pub target: PathTarget,
+    /// How long to wait after arriving, before moving on to the next stop
+    pub wait: Duration,
+}
+
+impl PatrolStop {
+    /// Create a `PatrolStop`
+    pub fn new(target: PathTarget, wait: Duration) -> Self {
+        Self { target, wait }
+    }
+}
+
+/// What a [`Patrol`] does after its last stop
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Reflect)]
+pub enum PatrolMode {
+    /// Go back to the first stop
+    Loop,
+    /// Visit the stops in reverse, and then forward again
+    PingPong,
+    /// Stay at the last stop
+    Once,
+}
+
+// Starts waiting when the navigator arrives at its stop
+fn wait_at_stop(trigger: Trigger<Arrived>, mut patrols: Query<&mut Patrol>, time: Res<Time>) {
+    let Ok(mut patrol) = patrols.get_mut(trigger.target()) else {
+        return;
+    };
+
+    let Some(stop) = patrol.stops.get(patrol.current) else {
+        return;
+    };
+
+    patrol.leave_at = Some(time.elapsed() + stop.wait);
+}
+
+// Moves on from stops that can't be reached
+fn skip_failed_stop(trigger: Trigger<PathFailed>, mut patrols: Query<&mut Patrol>) {
+    let Ok(mut patrol) = patrols.get_mut(trigger.target()) else {
+        return;
+    };
+
+    // The stop being waited at was already reached
+    if patrol.leave_at.is_none() && !patrol.done {
+        patrol.advance();
+    }
+}
+
+// Sends navigators to their next stops
+fn patrol(mut patrols: Query<(&mut Patrol, &mut Pathfind)>, time: Res<Time>) {
+    for (mut patrol, mut pathfind) in &mut patrols {
+        let leave = patrol
+            .leave_at
+            .is_some_and(|leave_at| leave_at <= time.elapsed());
+        if leave {
+            patrol.leave_at = None;
+            patrol.advance();
+        }
+
+        if patrol.done || patrol.leave_at.is_some() {
+            continue;
+        }
+
+        let Some(stop) = patrol.stops.get(patrol.current) else {
+            continue;
+        };
+
+        // The next stop may have the same target as the last
+        if leave || pathfind.target != stop.target {
+            pathfind.target = stop.target.clone();
+            pathfind.next_repath = Duration::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    // Stops visited by a patrol with this many stops, starting with the first
+    fn visits(mode: PatrolMode, stops: usize, count: usize) -> Vec<Option<usize>> {
+        let stop = PatrolStop::new(PathTarget::Static(Vec2::ZERO), Duration::ZERO);
+        let mut patrol = Patrol::new(vec![stop; stops], mode);
+        (0..count)
+            .map(|_| {
+                let current = (!patrol.done).then_some(patrol.current);
+                patrol.advance();
+                current
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loops_go_back_to_the_start() {
+        assert_eq!(
+            visits(PatrolMode::Loop, 3, 5),
+            [Some(0), Some(1), Some(2), Some(0), Some(1)]
+        );
+        assert_eq!(visits(PatrolMode::Loop, 2, 3), [Some(0), Some(1), Some(0)]);
+        assert_eq!(visits(PatrolMode::Loop, 1, 2), [Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn ping_pongs_turn_around_at_the_ends() {
+        assert_eq!(
+            visits(PatrolMode::PingPong, 3, 7),
+            [
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(1),
+                Some(0),
+                Some(1),
+                Some(2)
+            ]
+        );
+        assert_eq!(
+            visits(PatrolMode::PingPong, 2, 4),
+            [Some(0), Some(1), Some(0), Some(1)]
+        );
+        assert_eq!(visits(PatrolMode::PingPong, 1, 2), [Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn once_ends_after_the_last_stop() {
+        assert_eq!(
+            visits(PatrolMode::Once, 3, 4),
+            [Some(0), Some(1), Some(2), None]
+        );
+        assert_eq!(visits(PatrolMode::Once, 2, 3), [Some(0), Some(1), None]);
+        assert_eq!(visits(PatrolMode::Once, 1, 2), [Some(0), None]);
+    }
+
+    #[test]
+    fn stops_are_waited_at() {
+        let mut app = testing::app();
+        let map = testing::map(&mut app, UVec2::splat(8), |_| false, [0.]);
+        let (first, second) = (
+            PathTarget::Static(Vec2::new(2.5, 0.5)),
+            PathTarget::Static(Vec2::new(2.5, 4.5)),
+        );
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(10.),
+                testing::pathfind(map, 0., first.clone()),
+                Patrol::new(
+                    vec![
+                        PatrolStop::new(first.clone(), Duration::from_secs(1)),
+                        PatrolStop::new(second.clone(), Duration::ZERO),
+                    ],
+                    PatrolMode::Once,
+                ),
+            ),
+        );
+
+        testing::run(&mut app, 3);
+        let arrived = testing::position(&app, navigator);
+        assert!(arrived.distance(Vec2::new(2.5, 0.5)) < 1e-3);
+        assert!(testing::get::<Patrol>(&app, navigator).leave_at.is_some());
+
+        // It's still waiting a frame before the wait is over
+        testing::run(&mut app, 8);
+        assert_eq!(testing::get::<Pathfind>(&app, navigator).target, first);
+        assert_eq!(testing::position(&app, navigator), arrived);
+
+        testing::run(&mut app, 3);
+        assert_eq!(testing::get::<Pathfind>(&app, navigator).target, second);
+        assert!(testing::position(&app, navigator).y > arrived.y);
+    }
+
+    #[test]
+    fn unreachable_stops_are_skipped() {
+        let mut app = testing::app();
+        let map = testing::map(&mut app, UVec2::splat(8), |tile| tile.x >= 4, [0.]);
+        let (wall, open) = (
+            PathTarget::Static(Vec2::new(6.5, 0.5)),
+            PathTarget::Static(Vec2::new(2.5, 2.5)),
+        );
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav::new(10.),
+                testing::pathfind(map, 0., wall.clone()),
+                Patrol::new(
+                    vec![
+                        PatrolStop::new(wall, Duration::ZERO),
+                        PatrolStop::new(open.clone(), Duration::ZERO),
+                    ],
+                    PatrolMode::Once,
+                ),
+            ),
+        );
+
+        testing::run(&mut app, 5);
+        let patrol = testing::get::<Patrol>(&app, navigator);
+        assert_eq!(patrol.current, 1);
+        assert!(patrol.done);
+        assert_eq!(testing::get::<Pathfind>(&app, navigator).target, open);
+        assert!(testing::position(&app, navigator).distance(Vec2::new(2.5, 2.5)) < 1e-3);
+    }
+}
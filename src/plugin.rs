@@ -1,19 +1,56 @@
 use std::marker::PhantomData;
 
-use crate::{nav::plug, prelude::*};
+use navmesh::NavMesh;
 
-/// Add to your app to enable pathing and navigation. The type parameter accepts
-/// the position component used by your navigators.
+use crate::{
+    avoid, flow, formation, mesh::PathMesh, nav, patrol, prelude::*, set::MapNavSet, stuck,
+};
+
+/// Add to your app to enable pathing and navigation. The first type parameter accepts
+/// the position component used by your navigators. The second accepts the [`PathMesh`]
+/// stored in your map's [`Navmeshes`].
+#[derive(Debug)]
+pub struct MapNavPlugin<P: Position2 = Transform, M: PathMesh = NavMesh>(PhantomData<(P, M)>);
+
+impl<P: Position2, M: PathMesh> Plugin for MapNavPlugin<P, M> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            nav::plug::<P, M>,
+            flow::plug::<P, M>,
+            avoid::plug::<P, M>,
+            formation::plug::<P, M>,
+            stuck::plug::<P, M>,
+        ));
+        // Not added as a plugin, which would fail for the second `PathMesh`
+        patrol::plug(app);
+    }
+}
+
+impl<P: Position2, M: PathMesh> Default for MapNavPlugin<P, M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Add to your app to have navigators with [`VelocityNav`] set the given velocity component
+/// instead of their positions. Add a [`MapNavPlugin`] too.
 #[derive(Debug)]
-pub struct MapNavPlugin<P: Position2 = Transform>(PhantomData<P>);
+pub struct NavVelocityPlugin<V: Velocity2>(PhantomData<V>);
 
-impl<P: Position2> Plugin for MapNavPlugin<P> {
+impl<V: Velocity2> Plugin for NavVelocityPlugin<V> {
     fn build(&self, app: &mut App) {
-        app.add_plugins(plug::<P>);
+        app.add_systems(
+            Update,
+            (
+                nav::read_velocities::<V>.before(nav::NavStep::Nav),
+                nav::apply_velocities::<V>.after(nav::NavStep::Avoid),
+            )
+                .in_set(MapNavSet),
+        );
     }
 }
 
-impl<P: Position2> Default for MapNavPlugin<P> {
+impl<V: Velocity2> Default for NavVelocityPlugin<V> {
     fn default() -> Self {
         Self(PhantomData)
     }
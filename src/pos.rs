@@ -21,3 +21,12 @@ impl Position2 for Transform {
         self.translation = pos.extend(self.translation.z);
     }
 }
+
+/// Component that represents a 2D velocity, such as a physics body's. Navigators with
+/// [`VelocityNav`] navigate by setting it.
+pub trait Velocity2: Component<Mutability = Mutable> {
+    /// Get the velocity as a vector
+    fn get(&self) -> Vec2;
+    /// Set the velocity from a vector
+    fn set(&mut self, velocity: Vec2);
+}
@@ -0,0 +1,22 @@
+use std::cmp::Ordering;
+
+// Entry in a min-priority queue of graph nodes, for A* and Dijkstra
+#[derive(PartialEq)]
+pub(crate) struct Frontier {
+    pub(crate) priority: f32,
+    pub(crate) index: usize,
+}
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
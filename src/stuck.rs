@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use bevy_ecs::query::QueryEntityError;
+
+use crate::{
+    mesh::PathMesh,
+    nav::{NavStep, PathTask},
+    prelude::*,
+    set::MapNavSet,
+};
+
+pub(crate) fn plug<P: Position2, M: PathMesh>(app: &mut App) {
+    app.add_systems(
+        Update,
+        detect_stuck::<P, M>
+            .after(NavStep::Progress)
+            .in_set(MapNavSet),
+    );
+}
+
+/// Settings for telling that a navigator is stuck, such as when it's blocked by another
+/// navigator or pushed off the navmesh, and recovering. It's stuck when it moves less than
+/// `min_progress` within `window` while following a path.
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct StuckDetection {
+    /// How long the navigator has to make progress
+    pub window: Duration,
+    /// How far the navigator must move within each window
+    pub min_progress: f32,
+    /// What to do when the navigator is stuck
+    pub recovery: StuckRecovery,
+    // Time and position that the current window started at
+    start: Option<(Duration, Vec2)>,
+}
+
+impl StuckDetection {
+    /// Create a `StuckDetection`
+    pub fn new(window: Duration, min_progress: f32, recovery: StuckRecovery) -> Self {
+        Self {
+            window,
+            min_progress,
+            recovery,
+            start: None,
+        }
+    }
+}
+
+/// What a navigator does when it's stuck
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Reflect)]
+pub enum StuckRecovery {
+    /// Find the path again
+    Repath,
+    /// Move to the closest point on the navmesh, and find the path again
+    Snap,
+    /// Stop navigating until [`Pathfind::target`] changes. Triggers [`PathFailed`] with
+    /// [`PathError::Stuck`].
+    GiveUp,
+}
+
+#[allow(clippy::type_complexity)]
+fn detect_stuck<P: Position2, M: PathMesh>(
+    mut commands: Commands,
+    mut navs: Query<(
+        Entity,
+        &mut P,
+        &mut Pathfind,
+        &mut Nav,
+        Option<&mut VelocityNav>,
+    )>,
+    meshes: Query<&Navmeshes<M>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    for (entity, mut position, mut pathfind, mut nav, velocity_nav) in &mut navs {
+        let navmeshes = meshes.get(pathfind.map);
+        if let Err(QueryEntityError::QueryDoesNotMatch(..)) = navmeshes {
+            // The map is handled by another `PathMesh`
+            continue;
+        }
+
+        let following = !pathfind.path.is_empty() || !pathfind.coarse_path.is_empty();
+        let Some(stuck_detection) = &mut nav.stuck_detection else {
+            continue;
+        };
+        if !following {
+            stuck_detection.start = None;
+            continue;
+        }
+
+        let pos = position.get();
+        let (start, start_pos) = *stuck_detection.start.get_or_insert((now, pos));
+        if now.saturating_sub(start) < stuck_detection.window {
+            continue;
+        }
+
+        stuck_detection.start = Some((now, pos));
+        let progress = pos.distance(start_pos);
+        if progress >= stuck_detection.min_progress {
+            continue;
+        }
+
+        #[cfg(feature = "log")]
+        warn!("navigator {entity} is stuck");
+        commands.trigger_targets(Stuck { progress }, entity);
+
+        match stuck_detection.recovery {
+            StuckRecovery::Repath => pathfind.next_repath = Duration::ZERO,
+            StuckRecovery::Snap => {
+                let snapped = navmeshes
+                    .ok()
+                    .and_then(|navmeshes| navmeshes.mesh(pathfind.radius))
+                    .and_then(|mesh| mesh.closest_point(pos, pathfind.query));
+                if let Some(snapped) = snapped {
+                    position.set(snapped);
+                    if let Some(mut velocity_nav) = velocity_nav {
+                        velocity_nav.expected = None;
+                    }
+                }
+
+                pathfind.next_repath = Duration::ZERO;
+            }
+            StuckRecovery::GiveUp => {
+                // Stay stopped until the target changes, instead of repathing with
+                // `repath_frequency` or the `RepathPolicy`
+                commands.entity(entity).try_remove::<PathTask>();
+                pathfind.abandoned = Some(pathfind.target.clone());
+                pathfind.next_repath = Duration::MAX;
+                pathfind.pending = false;
+                pathfind.path.clear();
+                pathfind.coarse_path.clear();
+                pathfind.error = Some(PathError::Stuck);
+                nav.done = true;
+                nav.velocity = Vec2::ZERO;
+                commands.trigger_targets(PathFailed(PathError::Stuck), entity);
+
+                #[cfg(feature = "state")]
+                commands.entity(entity).insert(Done::Failure);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    // Spawns a navigator that can't move, on its way across an open map
+    fn blocked(recovery: StuckRecovery) -> (App, Entity) {
+        let mut app = testing::app();
+        testing::record::<Stuck>(&mut app);
+        testing::record::<PathFound>(&mut app);
+        testing::record::<PathFailed>(&mut app);
+        let map = testing::map(&mut app, UVec2::splat(8), |tile| tile.y == 7, [0.]);
+        let navigator = testing::spawn(
+            &mut app,
+            Vec2::new(0.5, 0.5),
+            (
+                Nav {
+                    stuck_detection: Some(StuckDetection::new(
+                        Duration::from_secs(1),
+                        0.5,
+                        recovery,
+                    )),
+                    ..Nav::new(0.)
+                },
+                testing::pathfind(map, 0., PathTarget::Static(Vec2::new(6.5, 0.5))),
+            ),
+        );
+
+        (app, navigator)
+    }
+
+    #[test]
+    fn blocked_navigators_are_stuck_after_the_window() {
+        let (mut app, navigator) = blocked(StuckRecovery::Repath);
+        testing::run(&mut app, 10);
+        assert!(testing::recorded::<Stuck>(&mut app, navigator).is_empty());
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+
+        testing::run(&mut app, 2);
+        let stuck = testing::recorded::<Stuck>(&mut app, navigator);
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].progress, 0.);
+        // The path is found again
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+        assert!(!testing::get::<Pathfind>(&app, navigator).path.is_empty());
+    }
+
+    #[test]
+    fn snapping_moves_navigators_onto_the_mesh() {
+        let (mut app, navigator) = blocked(StuckRecovery::Snap);
+        testing::run(&mut app, 2);
+        // Pushed into the wall
+        testing::get_mut::<Transform>(&mut app, navigator).translation = Vec3::new(0.5, 7.5, 0.);
+
+        // Being pushed counts as progress, so it's stuck by the end of the next window
+        testing::recorded::<PathFound>(&mut app, navigator);
+        testing::run(&mut app, 10);
+        assert!(testing::recorded::<Stuck>(&mut app, navigator).is_empty());
+        testing::run(&mut app, 11);
+        assert_eq!(testing::recorded::<Stuck>(&mut app, navigator).len(), 1);
+        assert!(testing::position(&app, navigator).distance(Vec2::new(0.5, 7.)) < 1e-3);
+        // The path is found again from there
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+    }
+
+    #[test]
+    fn giving_up_stops_until_the_target_changes() {
+        let (mut app, navigator) = blocked(StuckRecovery::GiveUp);
+        testing::get_mut::<Pathfind>(&mut app, navigator).repath_frequency =
+            Some(Duration::from_millis(100));
+        testing::run(&mut app, 12);
+        assert_eq!(testing::recorded::<Stuck>(&mut app, navigator).len(), 1);
+        let failed = testing::recorded::<PathFailed>(&mut app, navigator);
+        assert!(matches!(failed[..], [PathFailed(PathError::Stuck)]));
+        let pathfind = testing::get::<Pathfind>(&app, navigator);
+        assert_eq!(pathfind.error, Some(PathError::Stuck));
+        assert!(pathfind.path.is_empty());
+        assert!(testing::get::<Nav>(&app, navigator).done);
+
+        // It doesn't repath, despite its `repath_frequency`
+        testing::recorded::<PathFound>(&mut app, navigator);
+        testing::run(&mut app, 5);
+        assert!(testing::recorded::<PathFound>(&mut app, navigator).is_empty());
+        assert!(testing::get::<Pathfind>(&app, navigator).path.is_empty());
+
+        testing::get_mut::<Pathfind>(&mut app, navigator).target =
+            PathTarget::Static(Vec2::new(0.5, 6.5));
+        testing::run(&mut app, 1);
+        assert_eq!(testing::recorded::<PathFound>(&mut app, navigator).len(), 1);
+        assert!(!testing::get::<Nav>(&app, navigator).done);
+    }
+
+    #[test]
+    fn moving_navigators_are_not_stuck() {
+        let (mut app, navigator) = blocked(StuckRecovery::GiveUp);
+        testing::get_mut::<Nav>(&mut app, navigator).speed = 1.;
+        testing::run(&mut app, 70);
+        assert!(testing::get::<Nav>(&app, navigator).done);
+
+        // Waiting without a path doesn't count toward the next window
+        testing::run(&mut app, 20);
+        let mut pathfind = testing::get_mut::<Pathfind>(&mut app, navigator);
+        pathfind.target = PathTarget::Static(Vec2::new(0.5, 0.5));
+        pathfind.next_repath = Duration::ZERO;
+        testing::run(&mut app, 70);
+        assert!(testing::position(&app, navigator).distance(Vec2::new(0.5, 0.5)) < 1e-3);
+        assert!(testing::recorded::<Stuck>(&mut app, navigator).is_empty());
+        assert!(testing::recorded::<PathFailed>(&mut app, navigator).is_empty());
+    }
+}
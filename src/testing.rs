@@ -0,0 +1,126 @@
+// Helpers for tests that run navigators in an app
+
+use std::time::Duration;
+
+use bevy_ecs::component::Mutable;
+use bevy_time::{TimePlugin, TimeUpdateStrategy};
+
+use crate::prelude::*;
+
+// Length of each frame
+pub(crate) const FRAME: Duration = Duration::from_millis(100);
+
+// App with a `MapNavPlugin`, where each update advances time by `FRAME`
+pub(crate) fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((TimePlugin, MapNavPlugin::<Transform>::default()))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+    // The first update doesn't advance time
+    app.update();
+    app
+}
+
+// Spawns a map of 1x1 tiles with navmeshes for each clearance, where `solid` tiles are walls
+pub(crate) fn map(
+    app: &mut App,
+    size: UVec2,
+    solid: impl Fn(UVec2) -> bool,
+    clearances: impl IntoIterator<Item = f32>,
+) -> Entity {
+    let navmeshes = Navmeshes::generate(
+        size,
+        Vec2::ONE,
+        |tile| match solid(tile) {
+            true => Navability::Solid,
+            false => Navability::Navable,
+        },
+        clearances,
+    )
+    .unwrap();
+    app.world_mut().spawn(navmeshes).id()
+}
+
+// `Pathfind` that finds its path once
+pub(crate) fn pathfind(map: Entity, radius: f32, target: PathTarget) -> Pathfind {
+    Pathfind::new(
+        map,
+        radius,
+        None,
+        target,
+        NavQuery::Accuracy,
+        NavPathMode::Accuracy,
+    )
+}
+
+// Spawns a navigator at `pos`
+pub(crate) fn spawn(app: &mut App, pos: Vec2, bundle: impl Bundle) -> Entity {
+    app.world_mut()
+        .spawn((Transform::from_translation(pos.extend(0.)), bundle))
+        .id()
+}
+
+pub(crate) fn position(app: &App, entity: Entity) -> Vec2 {
+    app.world().get::<Transform>(entity).unwrap().get()
+}
+
+pub(crate) fn get<C: Component>(app: &App, entity: Entity) -> &C {
+    app.world().get::<C>(entity).unwrap()
+}
+
+pub(crate) fn get_mut<C: Component<Mutability = Mutable>>(
+    app: &mut App,
+    entity: Entity,
+) -> Mut<'_, C> {
+    app.world_mut().get_mut::<C>(entity).unwrap()
+}
+
+pub(crate) fn run(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+// Events of type `E` triggered on entities, in order
+#[derive(Resource)]
+pub(crate) struct Recorded<E>(pub(crate) Vec<(Entity, E)>);
+
+// Records the events of type `E` that are triggered from now on
+pub(crate) fn record<E: Event + Clone>(app: &mut App) {
+    app.insert_resource(Recorded::<E>(Vec::new())).add_observer(
+        |trigger: Trigger<E>, mut recorded: ResMut<Recorded<E>>| {
+            recorded.0.push((trigger.target(), trigger.event().clone()));
+        },
+    );
+}
+
+// Takes the events of type `E` that were recorded on `entity`
+pub(crate) fn recorded<E: Event + Clone>(app: &mut App, entity: Entity) -> Vec<E> {
+    let mut recorded = app.world_mut().resource_mut::<Recorded<E>>();
+    let (events, rest) = recorded
+        .0
+        .drain(..)
+        .partition::<Vec<_>, _>(|&(target, _)| target == entity);
+    recorded.0 = rest;
+    events.into_iter().map(|(_, event)| event).collect()
+}
+
+// Velocity of a body, which `move_bodies` moves it by, like a physics engine would
+#[derive(Clone, Component, Copy, Default)]
+pub(crate) struct Velocity(pub(crate) Vec2);
+
+impl Velocity2 for Velocity {
+    fn get(&self) -> Vec2 {
+        self.0
+    }
+
+    fn set(&mut self, velocity: Vec2) {
+        self.0 = velocity;
+    }
+}
+
+// Moves bodies by their velocities. Add it before `MapNavSet`.
+pub(crate) fn move_bodies(mut bodies: Query<(&mut Transform, &Velocity)>, time: Res<Time>) {
+    for (mut transform, velocity) in &mut bodies {
+        transform.translation += (velocity.0 * time.delta_secs()).extend(0.);
+    }
+}
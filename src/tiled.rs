@@ -0,0 +1,680 @@
+//! Loading navability from [Tiled](https://www.mapeditor.org/) maps
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    f32::consts::{SQRT_2, TAU},
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use glam::{UVec2, Vec2};
+use roxmltree::{Document, Node};
+
+use crate::mesh::{Navability, NavmeshGenError, Navmeshes};
+
+// Bits of a global tile ID that flip or rotate the tile
+const FLIP_FLAGS: u32 = 0xF0000000;
+
+/// Where to find which tiles of a Tiled map are solid
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TiledNavability {
+    /// Tiles are solid if, on any tile layer, they are a tileset tile with this boolean
+    /// property set to `true`
+    Property(String),
+    /// Tiles are solid if they are set on the tile layers with this name, or if their centers
+    /// are inside an object on the object layers with this name. Rectangles, ellipses,
+    /// polygons, and tile objects are supported.
+    Layer(String),
+}
+
+/// Orientation of a Tiled map
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Orientation {
+    /// Square or rectangular tiles
+    Orthogonal,
+    /// Diamond-shaped tiles. The navmesh is square, so convert positions with
+    /// [`TiledMap::to_pixel`] and [`TiledMap::from_pixel`].
+    Isometric,
+}
+
+/// Navability of a Tiled map's tiles. Tile row 0 is the top row, like in Tiled.
+/// Tiled's y axis points down, so by default, row 0 is at y = 0 on the navmesh, and the map is
+/// upside down in a world where y points up, like Bevy's. Set `y_up` to place it upright.
+#[derive(Clone, Debug)]
+pub struct TiledMap {
+    /// Size of the map in tiles
+    pub map_size: UVec2,
+    /// Size of each tile in Tiled, in pixels
+    pub tile_size: Vec2,
+    /// Orientation of the map
+    pub orientation: Orientation,
+    /// Navability of each tile, row by row
+    pub tiles: Vec<Navability>,
+    /// Whether y points up on the navmesh, so row 0 is at the top. Otherwise, row 0 is at
+    /// y = 0, like in Tiled. [`TiledMap::to_pixel`] and [`TiledMap::from_pixel`] account for it.
+    pub y_up: bool,
+}
+
+impl TiledMap {
+    /// Load a `.tmx` map. Tilesets in `.tsx` files are loaded relative to the map.
+    pub fn load(path: impl AsRef<Path>, navability: &TiledNavability) -> Result<Self, TiledError> {
+        let path = path.as_ref();
+        Self::parse(
+            &fs::read_to_string(path)?,
+            path.parent().unwrap_or(Path::new("")),
+            navability,
+        )
+    }
+
+    /// Parse the contents of a `.tmx` map. Tilesets in `.tsx` files are loaded relative
+    /// to `directory`.
+    pub fn parse(
+        tmx: &str,
+        directory: &Path,
+        navability: &TiledNavability,
+    ) -> Result<Self, TiledError> {
+        let document = Document::parse(tmx)?;
+        let map = document.root_element();
+        if !map.has_tag_name("map") {
+            return Err(TiledError::Invalid(
+                "the root element is not a map".to_string(),
+            ));
+        }
+        if map.attribute("infinite") == Some("1") {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+
+        let orientation = match map.attribute("orientation") {
+            Some("orthogonal") | None => Orientation::Orthogonal,
+            Some("isometric") => Orientation::Isometric,
+            Some(orientation) => {
+                return Err(TiledError::Unsupported(format!("{orientation} maps")));
+            }
+        };
+        let map_size = UVec2::new(attribute(map, "width")?, attribute(map, "height")?);
+        let tile_size = Vec2::new(attribute(map, "tilewidth")?, attribute(map, "tileheight")?);
+
+        let mut tiled_map = Self {
+            map_size,
+            tile_size,
+            orientation,
+            tiles: vec![Navability::Navable; (map_size.x * map_size.y) as usize],
+            y_up: false,
+        };
+
+        match navability {
+            TiledNavability::Property(property) => {
+                let solid = solid_tiles(map, directory, property)?;
+                for layer in layers(map, "layer", None) {
+                    tiled_map.mark_tiles(layer, |gid| solid.contains(&gid))?;
+                }
+            }
+            TiledNavability::Layer(name) => {
+                for layer in layers(map, "layer", Some(name)) {
+                    tiled_map.mark_tiles(layer, |gid| gid != 0)?;
+                }
+                for layer in layers(map, "objectgroup", Some(name)) {
+                    tiled_map.mark_objects(layer)?;
+                }
+            }
+        }
+
+        Ok(tiled_map)
+    }
+
+    /// Navability of the tile at the given position
+    pub fn navability(&self, position: UVec2) -> Navability {
+        self.tiles[(position.y * self.map_size.x + position.x) as usize]
+    }
+
+    /// Size of each tile on the navmesh. Isometric tiles are square on the navmesh, with sides
+    /// of the tile width over √2, so the square's diagonal is as wide as the diamond. The tile
+    /// height doesn't affect it.
+    pub fn navmesh_tile_size(&self) -> Vec2 {
+        match self.orientation {
+            Orientation::Orthogonal => self.tile_size,
+            Orientation::Isometric => Vec2::splat(self.tile_size.x / SQRT_2),
+        }
+    }
+
+    /// Generate navmeshes for the map, with each of the given clearances
+    pub fn navmeshes(
+        &self,
+        clearances: impl IntoIterator<Item = f32>,
+    ) -> Result<Navmeshes, NavmeshGenError> {
+        Navmeshes::generate(
+            self.map_size,
+            self.navmesh_tile_size(),
+            |position| match self.y_up {
+                true => self.navability(UVec2::new(position.x, self.map_size.y - 1 - position.y)),
+                false => self.navability(position),
+            },
+            clearances,
+        )
+    }
+
+    /// Convert a point on the navmesh to Tiled's pixel coordinates, where the top left corner
+    /// of the map is at 0, 0 and y points down
+    pub fn to_pixel(&self, point: Vec2) -> Vec2 {
+        let tile = self.flip(point / self.navmesh_tile_size());
+        match self.orientation {
+            Orientation::Orthogonal => tile * self.tile_size,
+            Orientation::Isometric => Vec2::new(
+                (tile.x - tile.y + self.map_size.y as f32) * self.tile_size.x / 2.,
+                (tile.x + tile.y) * self.tile_size.y / 2.,
+            ),
+        }
+    }
+
+    /// Convert a point in Tiled's pixel coordinates to a point on the navmesh.
+    /// The inverse of [`TiledMap::to_pixel`].
+    pub fn from_pixel(&self, pixel: Vec2) -> Vec2 {
+        let tile = match self.orientation {
+            Orientation::Orthogonal => pixel / self.tile_size,
+            Orientation::Isometric => {
+                let difference = pixel.x / (self.tile_size.x / 2.) - self.map_size.y as f32;
+                let sum = pixel.y / (self.tile_size.y / 2.);
+                Vec2::new(sum + difference, sum - difference) / 2.
+            }
+        };
+
+        self.flip(tile) * self.navmesh_tile_size()
+    }
+
+    // Converts a position in tiles between Tiled's rows and the navmesh's, if they differ
+    fn flip(&self, tile: Vec2) -> Vec2 {
+        match self.y_up {
+            true => Vec2::new(tile.x, self.map_size.y as f32 - tile.y),
+            false => tile,
+        }
+    }
+
+    // Marks the tiles whose global tile IDs in the tile layer match as solid
+    fn mark_tiles(&mut self, layer: Node, solid: impl Fn(u32) -> bool) -> Result<(), TiledError> {
+        let data = layer
+            .children()
+            .find(|child| child.has_tag_name("data"))
+            .ok_or_else(|| TiledError::Invalid("a tile layer has no data".to_string()))?;
+        let gids = tile_data(data)?;
+        if gids.len() != self.tiles.len() {
+            return Err(TiledError::Invalid(format!(
+                "a tile layer has {} tiles, but the map has {}",
+                gids.len(),
+                self.tiles.len(),
+            )));
+        }
+
+        for (tile, gid) in self.tiles.iter_mut().zip(gids) {
+            if solid(gid & !FLIP_FLAGS) {
+                *tile = Navability::Solid;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Marks the tiles whose centers are inside the object layer's objects as solid
+    fn mark_objects(&mut self, layer: Node) -> Result<(), TiledError> {
+        // Object positions are in pixels. On isometric maps, both axes are measured
+        // in tile heights.
+        let pixels_per_tile = match self.orientation {
+            Orientation::Orthogonal => self.tile_size,
+            Orientation::Isometric => Vec2::splat(self.tile_size.y),
+        };
+
+        for object in layer
+            .children()
+            .filter(|child| child.has_tag_name("object"))
+        {
+            let Some(polygon) = object_polygon(object)? else {
+                continue;
+            };
+            let polygon = polygon
+                .into_iter()
+                .map(|point| point / pixels_per_tile)
+                .collect::<Vec<_>>();
+
+            let min = polygon
+                .iter()
+                .copied()
+                .reduce(Vec2::min)
+                .unwrap_or_default();
+            let max = polygon
+                .iter()
+                .copied()
+                .reduce(Vec2::max)
+                .unwrap_or_default();
+            let min = min.floor().max(Vec2::ZERO).as_uvec2();
+            let max = max.ceil().as_uvec2().min(self.map_size);
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    if contains(&polygon, UVec2::new(x, y).as_vec2() + 0.5) {
+                        self.tiles[(y * self.map_size.x + x) as usize] = Navability::Solid;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Error that can emit when loading a Tiled map
+#[derive(Debug)]
+pub enum TiledError {
+    /// Error reading the map or a tileset
+    Io(io::Error),
+    /// Error parsing the XML of the map or a tileset
+    Xml(roxmltree::Error),
+    /// The map or a tileset is malformed
+    Invalid(String),
+    /// The map uses a feature that isn't supported
+    Unsupported(String),
+}
+
+impl Display for TiledError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read Tiled file: {error}"),
+            Self::Xml(error) => write!(f, "could not parse Tiled file: {error}"),
+            Self::Invalid(reason) => write!(f, "invalid Tiled map: {reason}"),
+            Self::Unsupported(feature) => write!(f, "{feature} are not supported"),
+        }
+    }
+}
+
+impl Error for TiledError {}
+
+impl From<io::Error> for TiledError {
+    fn from(error: io::Error) -> Self {
+        TiledError::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for TiledError {
+    fn from(error: roxmltree::Error) -> Self {
+        TiledError::Xml(error)
+    }
+}
+
+fn attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, TiledError> {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            TiledError::Invalid(format!(
+                "`{}` has a missing or invalid `{name}`",
+                node.tag_name().name(),
+            ))
+        })
+}
+
+// Layers of the given kind, including those in groups, with the given name, if any
+fn layers<'a, 'input>(
+    map: Node<'a, 'input>,
+    kind: &'a str,
+    name: Option<&'a str>,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    map.descendants().filter(move |node| {
+        node.has_tag_name(kind) && name.is_none_or(|name| node.attribute("name") == Some(name))
+    })
+}
+
+// Global tile IDs of the tileset tiles that have the boolean property set to `true`
+fn solid_tiles(map: Node, directory: &Path, property: &str) -> Result<HashSet<u32>, TiledError> {
+    let mut solid = HashSet::new();
+    for tileset in map.children().filter(|child| child.has_tag_name("tileset")) {
+        let first_gid = attribute::<u32>(tileset, "firstgid")?;
+        match tileset.attribute("source") {
+            Some(source) => {
+                let tsx = fs::read_to_string(directory.join(source))?;
+                let document = Document::parse(&tsx)?;
+                mark_solid(document.root_element(), first_gid, property, &mut solid)?;
+            }
+            None => mark_solid(tileset, first_gid, property, &mut solid)?,
+        }
+    }
+
+    Ok(solid)
+}
+
+fn mark_solid(
+    tileset: Node,
+    first_gid: u32,
+    property: &str,
+    solid: &mut HashSet<u32>,
+) -> Result<(), TiledError> {
+    for tile in tileset
+        .children()
+        .filter(|child| child.has_tag_name("tile"))
+    {
+        let set = tile
+            .descendants()
+            .filter(|node| node.has_tag_name("property"))
+            .any(|node| {
+                node.attribute("name") == Some(property) && node.attribute("value") == Some("true")
+            });
+        if set {
+            solid.insert(first_gid + attribute::<u32>(tile, "id")?);
+        }
+    }
+
+    Ok(())
+}
+
+// Global tile IDs of a tile layer's data, row by row
+fn tile_data(data: Node) -> Result<Vec<u32>, TiledError> {
+    let text = data.text().unwrap_or_default().trim();
+    match data.attribute("encoding") {
+        None => data
+            .children()
+            .filter(|child| child.has_tag_name("tile"))
+            .map(|tile| tile.attribute("gid").map_or(Ok(0), str::parse))
+            .collect::<Result<_, _>>()
+            .map_err(|error| TiledError::Invalid(format!("invalid tile: {error}"))),
+        Some("csv") => text
+            .split(',')
+            .map(|gid| gid.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|error| TiledError::Invalid(format!("invalid CSV tile data: {error}"))),
+        Some("base64") => {
+            let bytes = STANDARD.decode(text).map_err(|error| {
+                TiledError::Invalid(format!("invalid base64 tile data: {error}"))
+            })?;
+            let mut decompressed = Vec::new();
+            let bytes = match data.attribute("compression") {
+                None => bytes,
+                Some("zlib") => {
+                    ZlibDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                Some("gzip") => {
+                    GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                Some(compression) => {
+                    return Err(TiledError::Unsupported(format!(
+                        "{compression} compressed layers"
+                    )));
+                }
+            };
+
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(encoding) => Err(TiledError::Unsupported(format!(
+            "{encoding} encoded layers"
+        ))),
+    }
+}
+
+// Outline of an object in pixels, if it has an area
+fn object_polygon(object: Node) -> Result<Option<Vec<Vec2>>, TiledError> {
+    let position = Vec2::new(attribute(object, "x")?, attribute(object, "y")?);
+    let size = Vec2::new(
+        object
+            .attribute("width")
+            .map_or(Ok(0.), |_| attribute(object, "width"))?,
+        object
+            .attribute("height")
+            .map_or(Ok(0.), |_| attribute(object, "height"))?,
+    );
+    let shape = object
+        .children()
+        .find(|child| child.is_element() && !child.has_tag_name("properties"));
+
+    let polygon = match shape.map(|shape| shape.tag_name().name()) {
+        Some("point" | "polyline" | "text") => return Ok(None),
+        Some("polygon") => shape
+            .and_then(|shape| shape.attribute("points"))
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|point| {
+                let (x, y) = point.split_once(',')?;
+                Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| TiledError::Invalid("invalid polygon points".to_string()))?,
+        Some("ellipse") => (0..16)
+            .map(|index| {
+                let angle = index as f32 / 16. * TAU;
+                size / 2. * (1. + Vec2::from_angle(angle))
+            })
+            .collect(),
+        // Tile objects are anchored at their bottom left corner
+        _ if object.attribute("gid").is_some() => {
+            vec![
+                Vec2::new(0., -size.y),
+                Vec2::new(size.x, -size.y),
+                Vec2::new(size.x, 0.),
+                Vec2::ZERO,
+            ]
+        }
+        _ => vec![
+            Vec2::ZERO,
+            Vec2::new(size.x, 0.),
+            size,
+            Vec2::new(0., size.y),
+        ],
+    };
+
+    if polygon.len() < 3 {
+        return Ok(None);
+    }
+
+    // Objects rotate clockwise, in degrees, around their position
+    let rotation = Vec2::from_angle(
+        object
+            .attribute("rotation")
+            .map_or(Ok(0.), |_| attribute::<f32>(object, "rotation"))?
+            .to_radians(),
+    );
+    Ok(Some(
+        polygon
+            .into_iter()
+            .map(|point| position + rotation.rotate(point))
+            .collect(),
+    ))
+}
+
+// Whether the point is inside the polygon, by the even-odd rule
+fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (index, &start) in polygon.iter().enumerate() {
+        let end = polygon[(index + 1) % polygon.len()];
+        if (start.y > point.y) != (end.y > point.y)
+            && point.x < start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x)
+        {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use navmesh::{NavPathMode, NavQuery};
+
+    use crate::mesh::PathMesh;
+
+    use super::*;
+
+    fn assets() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tiled"))
+    }
+
+    fn solid() -> TiledNavability {
+        TiledNavability::Property("solid".to_string())
+    }
+
+    // Rows of the map, where `#` is a solid tile
+    fn rows(map: &TiledMap) -> Vec<String> {
+        (0..map.map_size.y)
+            .map(|y| {
+                (0..map.map_size.x)
+                    .map(|x| match map.navability(UVec2::new(x, y)) {
+                        Navability::Navable => '.',
+                        Navability::Solid => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // 3x2 map with an embedded tileset, where tile 1 is solid, and the given tile layer data
+    fn small_map(data: &str) -> String {
+        format!(
+            r#"<map orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16">
+                <tileset firstgid="1">
+                    <tile id="1">
+                        <properties><property name="solid" type="bool" value="true"/></properties>
+                    </tile>
+                </tileset>
+                <layer name="Ground" width="3" height="2">{data}</layer>
+            </map>"#
+        )
+    }
+
+    #[test]
+    fn dungeon_walls_are_solid() {
+        let dungeon = TiledMap::load(assets().join("dungeon.tmx"), &solid()).unwrap();
+        assert_eq!(dungeon.orientation, Orientation::Orthogonal);
+        assert_eq!(dungeon.map_size, UVec2::new(16, 10));
+        assert_eq!(dungeon.tile_size, Vec2::splat(32.));
+        assert_eq!(
+            rows(&dungeon),
+            [
+                "################",
+                "#......#.......#",
+                "#......#.......#",
+                "#..............#",
+                "#......#.......#",
+                "####.###########",
+                "#..............#",
+                "#..............#",
+                "#..............#",
+                "################",
+            ],
+        );
+    }
+
+    #[test]
+    fn dungeon_collision_objects_are_solid() {
+        let dungeon = TiledMap::load(
+            assets().join("dungeon.tmx"),
+            &TiledNavability::Layer("Collision".to_string()),
+        )
+        .unwrap();
+        let solid = [
+            (2, 2),
+            (3, 2),
+            (11, 3),
+            (12, 3),
+            (11, 4),
+            (12, 4),
+            (3, 7),
+            (4, 7),
+            (5, 7),
+            (9, 7),
+            (4, 8),
+            (10, 8),
+        ];
+        for y in 0..dungeon.map_size.y {
+            for x in 0..dungeon.map_size.x {
+                assert_eq!(
+                    dungeon.navability(UVec2::new(x, y)) == Navability::Solid,
+                    solid.contains(&(x, y)),
+                    "tile {x}, {y}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn courtyard_zlib_data_with_flipped_tiles() {
+        let courtyard = TiledMap::load(assets().join("courtyard.tmx"), &solid()).unwrap();
+        assert_eq!(courtyard.orientation, Orientation::Isometric);
+        assert_eq!(
+            rows(&courtyard),
+            [
+                "##########",
+                "#........#",
+                "#..##....#",
+                "#..##....#",
+                "#........#",
+                "#.....#..#",
+                "#.....#..#",
+                "#..####..#",
+                "#........#",
+                "##########",
+            ],
+        );
+    }
+
+    #[test]
+    fn tile_data_encodings() {
+        for data in [
+            r#"<data encoding="csv">1,2,0,2147483650,1,1</data>"#,
+            r#"<data encoding="base64">AQAAAAIAAAAAAAAAAgAAgAEAAAABAAAA</data>"#,
+            r#"<data encoding="base64" compression="zlib">eJxjZGBgYGKAACDdwAikQRgABPwAiA==</data>"#,
+            r#"<data encoding="base64" compression="gzip">
+                H4sIAAAAAAACA2NkYGBgYoAAIN3ACKRBGACgIFIWGAAAAA==
+            </data>"#,
+            r#"<data>
+                <tile gid="1"/><tile gid="2"/><tile/>
+                <tile gid="2147483650"/><tile gid="1"/><tile gid="1"/>
+            </data>"#,
+        ] {
+            let map = TiledMap::parse(&small_map(data), Path::new(""), &solid()).unwrap();
+            assert_eq!(rows(&map), [".#.", "#.."], "{data}");
+        }
+
+        let error = TiledMap::parse(
+            &small_map(r#"<data encoding="csv">1,2,0</data>"#),
+            Path::new(""),
+            &solid(),
+        );
+        assert!(matches!(error, Err(TiledError::Invalid(_))));
+    }
+
+    #[test]
+    fn y_up_places_row_0_at_the_top() {
+        let mut dungeon = TiledMap::load(assets().join("dungeon.tmx"), &solid()).unwrap();
+        // The door in the middle wall, in Tiled's pixel coordinates
+        let door = Vec2::new(4.5, 5.5) * 32.;
+        assert_eq!(dungeon.from_pixel(door), door);
+
+        dungeon.y_up = true;
+        let point = dungeon.from_pixel(door);
+        assert_eq!(point, Vec2::new(4.5, 4.5) * 32.);
+        assert_eq!(dungeon.to_pixel(point), door);
+
+        let navmeshes = dungeon.navmeshes([0.]).unwrap();
+        let mesh = navmeshes.mesh(0.).unwrap();
+        let top_left = dungeon.from_pixel(Vec2::splat(48.));
+        assert!(top_left.y > 8. * 32.);
+        assert!(PathMesh::find_path(
+            mesh,
+            top_left,
+            point,
+            NavQuery::Accuracy,
+            NavPathMode::Accuracy
+        )
+        .is_some());
+        // The solid tile next to the door
+        let wall = Vec2::new(3.5, 4.5) * 32.;
+        assert!(mesh.distance_to_mesh(wall, NavQuery::Accuracy).unwrap() > 15.);
+
+        let mut courtyard = TiledMap::load(assets().join("courtyard.tmx"), &solid()).unwrap();
+        courtyard.y_up = true;
+        let pixel = Vec2::new(500., 100.);
+        assert!((courtyard.to_pixel(courtyard.from_pixel(pixel)) - pixel).length() < 0.001);
+    }
+}